@@ -45,6 +45,7 @@ const twz_error_code NOT_CONNECTED = 6;
 const twz_error_code UNREACHABLE = 7;
 const twz_error_code REFUSED = 8;
 const twz_error_code NON_ATOMIC = 9;
+const twz_error_code BUFFER_TOO_SMALL = 10;
 
 // Naming
 const twz_error_code NOT_FOUND = 1;
@@ -70,6 +71,7 @@ const twz_error_code DATA_LOSS = 2;
 const twz_error_code DEVICE_ERROR = 3;
 const twz_error_code SEEK_FAILED = 4;
 const twz_error_code RESET = 5;
+const twz_error_code UNEXPECTED_EOF = 6;
 
 // Security
 const twz_error_code INVALID_KEY = 1;
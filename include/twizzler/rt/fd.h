@@ -19,12 +19,21 @@ struct create_options {
 
 const size_t NAME_DATA_MAX = 2048;
 
+/// A handle to a set of name roots (see name_root) that names are resolved against.
+typedef uint32_t name_context;
+
+/// The calling thread's name context: its override, if one is set with twz_rt_set_thread_name_context, otherwise the process-wide
+/// roots set with twz_rt_set_nameroot.
+const name_context NAME_CONTEXT_CURRENT = 0;
+
 /// Information for opening a file.
 struct open_info {
   /// Creation options
   struct create_options create;
   /// Operation flags
   uint32_t flags;
+  /// Name context to resolve the name against.
+  name_context context;
   /// Length of file name in bytes.
   size_t len;
   uint8_t name[NAME_DATA_MAX];
@@ -36,6 +45,11 @@ const uint8_t CREATE_KIND_EXISTING = 0;
 const uint8_t CREATE_KIND_NEW = 1;
 /// Open if it already exists, or create it if it doesn't.
 const uint8_t CREATE_KIND_EITHER = 2;
+/// Create a new persistent object without binding any name to it. The name must refer to an existing namespace, which determines
+/// where the object will be stored and is where it is expected to be linked. The id in create_options must be 0. The object can be
+/// bound to a name later by opening that name with CREATE_KIND_NEW and the object's ID in create_options. If the last descriptor
+/// for the object is closed before it is bound to any name, the object is deleted.
+const uint8_t CREATE_KIND_ANONYMOUS = 3;
 
 /// Open the file with read access.
 const uint32_t OPEN_FLAG_READ = 1;
@@ -47,6 +61,10 @@ const uint32_t OPEN_FLAG_TRUNCATE = 4;
 const uint32_t OPEN_FLAG_TAIL = 8;
 /// If the file is a symlink, open the link instead of the target.
 const uint32_t OPEN_FLAG_SYMLINK = 0x10;
+/// For open kinds that may wait, such as socket accept and connect, do not block. Accept returns WOULD_BLOCK if no connection is
+/// pending, and the descriptor becomes readable (WAIT_READ) when one arrives. Connect returns the descriptor immediately, which
+/// becomes writable (WAIT_WRITE) once the connection completes or fails. A failed connection is reported by the next I/O.
+const uint32_t OPEN_FLAG_NONBLOCKING = 0x20;
 
 /// Result of open call.
 struct open_result {
@@ -64,9 +82,13 @@ enum open_kind {
   OpenKind_SocketConnect,
   OpenKind_SocketBind,
   OpenKind_SocketAccept,
+  /// Open the server side of a pty backed by an object, given by bind_info, an object_bind_info. A new, empty object is
+  /// initialized as a pty with default terminal settings.
   OpenKind_PtyServer,
+  /// Open a client side of a pty backed by an object, given by bind_info, an object_bind_info.
   OpenKind_PtyClient,
   OpenKind_Compartment,
+  OpenKind_Watch,
 };
 
 enum addr_kind {
@@ -101,6 +123,33 @@ struct object_bind_info {
     objid id;
 };
 
+/// Events that can be reported by a watch descriptor.
+typedef uint32_t watch_mask;
+
+/// A name was created in the watched namespace.
+const watch_mask WATCH_CREATED = 1;
+/// A name was removed from the watched namespace, or the watched name itself was removed.
+const watch_mask WATCH_REMOVED = 2;
+/// A name in the watched namespace, or the watched name itself, was renamed.
+const watch_mask WATCH_RENAMED = 4;
+/// The data of a watched file, or of a file in the watched namespace, was modified.
+const watch_mask WATCH_MODIFIED = 8;
+/// The metadata (times, mode, extended attributes) of a watched entry changed.
+const watch_mask WATCH_ATTRIB = 0x10;
+/// Events were lost because the watch descriptor's queue overflowed. Always reported, regardless of mask.
+const watch_mask WATCH_OVERFLOW = 0x80000000;
+
+/// Bind info for OpenKind_Watch. The resulting descriptor is readable (with twz_rt_fd_pread) as a stream of
+/// whole watch_event structs, and signals readiness through twz_rt_fd_waitpoint (WAIT_READ), twz_rt_fd_poll, and twz_rt_fd_select.
+struct watch_bind_info {
+  /// Events to report.
+  watch_mask mask;
+  /// Length of name in bytes.
+  size_t len;
+  /// Name of the namespace or file to watch.
+  uint8_t name[NAME_DATA_MAX];
+};
+
 /// Open a non-named file. The value pointed to by bind_info is dependent on the kind specified in the first
 /// argument. For pipe, bind_info is ignored. For Socket* kinds, bind_info points to a socket_address.
 extern struct open_result twz_rt_fd_open(enum open_kind kind, uint32_t flags, void *bind_info, size_t bind_info_len);
@@ -131,11 +180,14 @@ enum fd_kind {
   FdKind_Pipe,
   FdKind_Pty,
   FdKind_Compartment,
+  /// Watch descriptor
+  FdKind_Watch,
 };
 
 /// Information about a file descriptor.
 struct fd_info {
-  /// Underlying root objid.
+  /// Underlying root objid. For a regular file, the file's data is stored at the start of this object's data, so byte i of the
+  /// file is at offset i from the start pointer of a mapping of the object.
   objid id;
   /// Length of underlying object, or 0 if undefined.
   uint64_t len;
@@ -147,6 +199,8 @@ struct fd_info {
   struct duration accessed;
   struct duration modified;
   uint32_t unix_mode;
+  /// Number of names bound to the underlying object, or 0 if undefined.
+  uint32_t nlink;
 };
 /// Get information about a descriptor. If this returns true, the fd was valid
 /// and the data pointed to by info is filled with fd_info data.
@@ -155,7 +209,8 @@ extern bool twz_rt_fd_get_info(descriptor fd, struct fd_info *info);
 /// Commands for descriptors.
 typedef uint32_t fd_cmd;
 
-/// Duplicate this descriptor. The arg argument is ignored. The ret argument points to a descriptor.
+/// Duplicate this descriptor. The arg argument is ignored. The ret argument points to a descriptor. The duplicate shares the
+/// original's waitpoints (see twz_rt_fd_waitpoint), which stay valid while either is open.
 const fd_cmd FD_CMD_DUP = 0;
 /// Sync the underlying storage of the file descriptor.
 const fd_cmd FD_CMD_SYNC = 1;
@@ -163,6 +218,72 @@ const fd_cmd FD_CMD_SYNC = 1;
 const fd_cmd FD_CMD_TRUNCATE = 2;
 /// Close either the read or write end of a file descriptor. The arg points to a u32, the first bit of which indicates read-side, the second indicates write.
 const fd_cmd FD_CMD_SHUTDOWN = 3;
+/// Set the accessed and modified times of the underlying file. The arg argument points to a struct fd_times.
+const fd_cmd FD_CMD_SET_TIMES = 4;
+/// Change the unix mode permission bits of the underlying file. The arg argument points to a u32 mode.
+const fd_cmd FD_CMD_CHMOD = 5;
+
+/// Acquire an advisory lock on the descriptor. The arg argument points to a struct fd_lock.
+const fd_cmd FD_CMD_LOCK = 6;
+/// Release an advisory lock on the descriptor. The arg argument points to a struct fd_lock, of which the kind, flags, and timeout fields are ignored.
+const fd_cmd FD_CMD_UNLOCK = 7;
+/// Allocate or deallocate storage for a range of the underlying file. The arg argument points to a struct fd_allocate.
+const fd_cmd FD_CMD_ALLOCATE = 8;
+
+/// Flags for FD_CMD_ALLOCATE.
+typedef uint32_t allocate_flags;
+
+/// Deallocate the range, turning it into a hole that reads as zeros. Never changes the file length.
+const allocate_flags ALLOCATE_FLAG_PUNCH_HOLE = 1;
+/// When allocating past the end of the file, do not extend the file length.
+const allocate_flags ALLOCATE_FLAG_KEEP_SIZE = 2;
+
+/// Argument to FD_CMD_ALLOCATE. Without flags, ensures storage is allocated for the range, extending the file length if the range
+/// ends past it. Fails with INVALID_ARGUMENT if the range extends past the maximum file size.
+struct fd_allocate {
+  /// Start of the range.
+  uint64_t offset;
+  /// Length of the range.
+  uint64_t len;
+  /// Flags, see ALLOCATE_FLAG_*.
+  allocate_flags flags;
+};
+
+/// Argument to FD_CMD_SET_TIMES. Times that are not set (is_some == 0) are left unchanged.
+struct fd_times {
+  /// New accessed time.
+  struct option_duration accessed;
+  /// New modified time.
+  struct option_duration modified;
+};
+
+/// Kinds of advisory locks.
+enum lock_kind {
+  /// Many holders may share the lock, but not with an exclusive holder.
+  LockKind_Shared,
+  /// Only one holder may hold the lock.
+  LockKind_Exclusive,
+};
+
+/// Flags for lock operations.
+typedef uint32_t lock_flags;
+
+/// If the lock is held by someone else, fail with Busy instead of waiting.
+const lock_flags LOCK_FLAG_NONBLOCKING = 1;
+
+/// Argument to FD_CMD_LOCK and FD_CMD_UNLOCK.
+struct fd_lock {
+  /// Kind of lock to acquire.
+  enum lock_kind kind;
+  /// Flags for the lock operation.
+  lock_flags flags;
+  /// Start of the locked byte range.
+  uint64_t start;
+  /// Length of the locked byte range. If 0, the range extends to the end of the file, even as the file grows.
+  uint64_t len;
+  /// Optional timeout for acquiring the lock. If flags contains LOCK_FLAG_NONBLOCKING, this argument is ignored.
+  struct option_duration timeout;
+};
 
 /// Perform a command on the descriptor. The arguments arg and ret are interpreted according to
 /// the command specified.
@@ -188,6 +309,19 @@ struct name_entry {
   uint8_t name[NAME_ENTRY_LEN];
 };
 
+/// An event read from a watch descriptor.
+struct watch_event {
+  /// The event that occurred, one of WATCH_*.
+  watch_mask kind;
+  /// Kind of the entry the event refers to.
+  enum fd_kind entry_kind;
+  /// Length of the entry's name, relative to the watched namespace. 0 if the event refers to the watched name itself.
+  uint32_t name_len;
+  /// For WATCH_RENAMED, the length of the entry's old name, stored in name after the new name. Otherwise 0.
+  uint32_t old_name_len;
+  uint8_t name[NAME_ENTRY_LEN];
+};
+
 /// Enumerate sub-names in an fd (e.g. directory entries). The buf and len arguments form a &mut [name_entry] slice, and the off argument specifies how many names to skip for this read. The return value is the number of entries read, or
 /// 0 if at end of list.
 extern struct io_result twz_rt_fd_enumerate_names(descriptor fd, struct name_entry *buf, size_t len, size_t off);
@@ -201,12 +335,60 @@ extern twz_error twz_rt_fd_mkns(const char *name, size_t name_len);
 /// Create a new symlink.
 extern twz_error twz_rt_fd_symlink(const char *name, size_t name_len, const char *target, size_t target_len);
 
+/// Create a new name, new_name, bound to the same object as old_name.
+extern twz_error twz_rt_fd_link(const char *old_name, size_t old_name_len, const char *new_name, size_t new_name_len);
+
 /// Rename a name in the namespace.
 extern twz_error twz_rt_fd_rename(const char *old_name, size_t old_name_len, const char *new_name, size_t new_name_len);
 
-/// Read symlink.
+/// Flags for twz_rt_fd_rename_with_flags.
+typedef uint32_t rename_flags;
+
+/// Fail with ALREADY_EXISTS instead of replacing new_name if it exists.
+const rename_flags RENAME_NOREPLACE = 1;
+/// Atomically swap the objects bound to old_name and new_name, which must both exist. May not be combined with RENAME_NOREPLACE.
+const rename_flags RENAME_EXCHANGE = 2;
+
+/// Rename a name in the namespace. The rename is atomic: at all times, including after a crash, new_name is bound either to the
+/// object it was bound to before the call, or to the object old_name was bound to. The change to the namespace is durable when
+/// this call returns successfully, but object data is not synced; sync the object (FD_CMD_SYNC) first to ensure new_name never
+/// refers to incompletely written data after a crash.
+extern twz_error twz_rt_fd_rename_with_flags(const char *old_name, size_t old_name_len, const char *new_name, size_t new_name_len, rename_flags flags);
+
+/// Read symlink. On success, *out_buf_len is set to the length of the link target. If buf is too small to hold the target,
+/// returns BUFFER_TOO_SMALL and sets *out_buf_len to the required length.
 extern twz_error twz_rt_fd_readlink(const char *name, size_t name_len, char *buf, size_t buf_len, uint64_t *out_buf_len);
 
+/// Maximum length of an extended attribute key, in bytes.
+const size_t XATTR_NAME_MAX = 255;
+/// Maximum length of an extended attribute value, in bytes.
+const size_t XATTR_VALUE_MAX = 4096;
+
+/// Flags for setting extended attributes.
+typedef uint32_t xattr_flags;
+
+/// Fail with AlreadyExists if the attribute already exists.
+const xattr_flags XATTR_FLAG_CREATE = 1;
+/// Fail with NotFound if the attribute does not already exist.
+const xattr_flags XATTR_FLAG_REPLACE = 2;
+
+/// Read the value of extended attribute key on a name into buf. On success, *out_len is set to the full length of the value, which may be larger than buf_len.
+/// Extended attributes are stored by the runtime alongside the object the name is bound to, and are not part of the object's data.
+extern twz_error twz_rt_fd_getxattr(const char *name, size_t name_len, const char *key, size_t key_len, void *buf, size_t buf_len, uint64_t *out_len);
+
+/// Set extended attribute key on a name. Keys are limited to XATTR_NAME_MAX bytes and may not contain NUL bytes, and values are limited to XATTR_VALUE_MAX bytes.
+extern twz_error twz_rt_fd_setxattr(const char *name, size_t name_len, const char *key, size_t key_len, const void *value, size_t value_len, xattr_flags flags);
+
+/// List the extended attribute keys of a name. The keys are written to buf, each terminated by a NUL byte. On success, *out_len is set to the full length of the list, which may be larger than buf_len.
+extern twz_error twz_rt_fd_listxattr(const char *name, size_t name_len, char *buf, size_t buf_len, uint64_t *out_len);
+
+/// Remove extended attribute key from a name.
+extern twz_error twz_rt_fd_removexattr(const char *name, size_t name_len, const char *key, size_t key_len);
+
+/// The roots that names are resolved from. A name is resolved from the root given by its prefix: a name starting with '/'
+/// is resolved from NameRoot_Root, and a name whose first component is "~", "~temp", or "~exe" is resolved from
+/// NameRoot_Home, NameRoot_Temp, or NameRoot_Exe. Any other name is resolved from NameRoot_Current; write "./~" to name an
+/// entry called "~" there.
 enum name_root {
     NameRoot_Root,
     NameRoot_Home,
@@ -215,10 +397,29 @@ enum name_root {
     NameRoot_Exe,
 };
 
+/// Set a process-wide name root. Threads with a name context override are not affected until the override is removed.
 extern twz_error twz_rt_set_nameroot(enum name_root root, const char *path, size_t path_len);
 
+/// Get a name root of the calling thread's name context. On success, val is the length of the root's name. If path is too
+/// small to hold the name, returns BUFFER_TOO_SMALL with val set to the required length.
 extern struct io_result twz_rt_get_nameroot(enum name_root root, char *path, size_t path_len);
 
+/// Create a new name context, starting with a copy of the calling thread's name roots. The handle is returned in val.
+extern struct u32_result twz_rt_name_context_create(void);
+
+/// Destroy a name context. Threads that use it as an override revert to the process-wide roots.
+extern void twz_rt_name_context_destroy(name_context ctx);
+
+/// Set a name root of a name context.
+extern twz_error twz_rt_name_context_set_root(name_context ctx, enum name_root root, const char *path, size_t path_len);
+
+/// Get a name root of a name context, with the same buffer semantics as twz_rt_get_nameroot.
+extern struct io_result twz_rt_name_context_get_root(name_context ctx, enum name_root root, char *path, size_t path_len);
+
+/// Override the name context used by the calling thread. Passing NAME_CONTEXT_CURRENT removes the override. Returns the previous
+/// override, or NAME_CONTEXT_CURRENT if there was none.
+extern name_context twz_rt_set_thread_name_context(name_context ctx);
+
 enum name_resolver {
     NameResolver_Default,
     NameResolver_Socket,
@@ -226,6 +427,12 @@ enum name_resolver {
 
 extern struct objid_result twz_rt_resolve_name(enum name_resolver resolver, const char *name, size_t name_len);
 
+/// Resolve a name against the roots of the given name context.
+extern struct objid_result twz_rt_resolve_name_in(name_context ctx, enum name_resolver resolver, const char *name, size_t name_len);
+
+/// Get the canonical form of a name. On input, *out_len is the size of the out buffer. On success, *out_len is set to the
+/// length of the canonical name. If out is too small to hold the name, returns BUFFER_TOO_SMALL and sets *out_len to the
+/// required length.
 extern twz_error twz_rt_canon_name(enum name_resolver resolver, const char *name, size_t name_len, char *out, size_t *out_len);
 
 #ifdef __cplusplus
@@ -30,6 +30,12 @@ const whence WHENCE_START = 0;
 const whence WHENCE_END = 1;
 /// Seek offset from current fd position
 const whence WHENCE_CURRENT = 2;
+/// Seek to the start of the first allocated (non-hole) range at or after offset, measured from the start of the file. Fails with
+/// SEEK_FAILED if there is no data at or after offset.
+const whence WHENCE_DATA = 3;
+/// Seek to the start of the first hole at or after offset, measured from the start of the file. The end of the file counts as a
+/// hole, so this succeeds for any offset within the file. Fails with SEEK_FAILED if offset is past the end of the file.
+const whence WHENCE_HOLE = 4;
 
 /// Optional offset. If value is FD_POS, use the file descriptor position.
 typedef int64_t optional_offset;
@@ -84,15 +90,109 @@ extern struct io_result twz_rt_fd_preadv(descriptor fd, const struct iovec *iovs
 /// Do vectored IO write.
 extern struct io_result twz_rt_fd_pwritev(descriptor fd, const struct iovec *iovs, size_t nr_iovs, struct io_ctx *ctx);
 
+/// Copy up to len bytes from src at src_off to dst at dst_off, without passing the data through the caller. Neither descriptor's
+/// position is used or updated. If both descriptors are backed by objects, the runtime copies between the objects directly,
+/// otherwise it copies through an internal buffer. Returns the number of bytes copied, which may be less than len, and is 0 if
+/// src_off is at or past the end of src. If an error occurs after some bytes are copied, the count of copied bytes is returned
+/// instead of the error.
+extern struct io_result twz_rt_fd_copy_range(descriptor src, uint64_t src_off, descriptor dst, uint64_t dst_off, size_t len);
+
+/// Operations for I/O queue submissions.
+typedef uint32_t io_op;
+
+/// Do nothing, and complete with val set to 0.
+const io_op IO_OP_NOP = 0;
+/// Read into buf, as twz_rt_fd_pread.
+const io_op IO_OP_READ = 1;
+/// Write from buf, as twz_rt_fd_pwrite.
+const io_op IO_OP_WRITE = 2;
+/// Read into the len iovecs at buf, as twz_rt_fd_preadv.
+const io_op IO_OP_READV = 3;
+/// Write from the len iovecs at buf, as twz_rt_fd_pwritev.
+const io_op IO_OP_WRITEV = 4;
+/// Seek by arg from whence, as twz_rt_fd_seek.
+const io_op IO_OP_SEEK = 5;
+/// Sync the descriptor, as FD_CMD_SYNC.
+const io_op IO_OP_SYNC = 6;
+/// Accept a connection on a listening socket, with open flags arg. On completion, val is the new descriptor.
+const io_op IO_OP_ACCEPT = 7;
+/// Connect a new socket to sock, with open flags arg. The fd field is ignored. On completion, val is the new descriptor.
+const io_op IO_OP_CONNECT = 8;
+
+/// An entry in an I/O queue's submission ring.
+struct io_sqe {
+  /// Operation, see IO_OP_*.
+  io_op op;
+  /// Descriptor to operate on.
+  descriptor fd;
+  /// Caller value, copied into the operation's completion.
+  uint64_t user_data;
+  /// Context for reads and writes.
+  struct io_ctx ctx;
+  /// Buffer for reads and writes, or array of iovecs for vectored reads and writes. Must remain valid until the operation completes.
+  void *buf;
+  /// Length of buf in bytes, or number of iovecs.
+  size_t len;
+  /// Seek offset, or open flags for accept and connect.
+  int64_t arg;
+  /// Whence for seek.
+  whence whence;
+  /// Address for connect.
+  struct socket_bind_info sock;
+};
+
+/// An entry in an I/O queue's completion ring.
+struct io_cqe {
+  /// The user_data of the submission.
+  uint64_t user_data;
+  /// Result of the operation.
+  struct io_result result;
+};
+
+/// Header at the start of an I/O queue object's data. The submission ring of sq_entries io_sqe structs starts sq_offset bytes
+/// from the start of the object's data, and the completion ring of cq_entries io_cqe structs starts cq_offset bytes from it.
+/// Entry counts are powers of two. Head and tail indices increase monotonically, and index the rings modulo the entry count.
+/// The caller produces submissions at sq_tail and consumes completions at cq_head; the runtime consumes submissions at sq_head
+/// and produces completions at cq_tail.
+struct io_queue_header {
+  uint32_t sq_entries;
+  uint32_t cq_entries;
+  uint64_t sq_offset;
+  uint64_t cq_offset;
+  _Atomic uint64_t sq_head;
+  _Atomic uint64_t sq_tail;
+  _Atomic uint64_t cq_head;
+  _Atomic uint64_t cq_tail;
+};
+
+/// Create an I/O queue object, with at least the given numbers of submission and completion entries. The object can be mapped
+/// read-write by the caller.
+extern struct objid_result twz_rt_io_queue_create(uint32_t sq_entries, uint32_t cq_entries);
+/// Start up to to_submit operations from the queue's submission ring, then wait until at least min_complete completions are
+/// available or the timeout expires. Operations may complete in any order. Returns the number of submissions consumed. If the
+/// completion ring is full, operations are not started until completions are consumed.
+extern struct io_result twz_rt_io_queue_enter(objid queue, uint32_t to_submit, uint32_t min_complete, struct option_duration timeout);
+/// Destroy an I/O queue. Operations that have not started are cancelled, and operations in progress are cancelled if possible.
+/// Does not return until every operation has either completed or been cancelled, so the runtime no longer accesses any
+/// submitted buffer once this call returns.
+extern twz_error twz_rt_io_queue_destroy(objid queue);
+
 typedef uint32_t wait_kind;
 const wait_kind WAIT_READ = 1;
 const wait_kind WAIT_WRITE = 2;
+/// Wait for signals (see IO_REGISTER_SIGNAL). The descriptor is ready if any signal is pending. The waitpoint's word changes
+/// only when a signal is raised, so waiting on it with the returned value sleeps until a new signal arrives, even while
+/// earlier signals are still pending.
+const wait_kind WAIT_SIGNAL = 3;
 
 /// Get a word and value to wait on for determining if reads or writes are available.
 extern twz_error twz_rt_fd_waitpoint(descriptor fd, wait_kind ek, uint64_t **point, uint64_t *val, _Bool *ready);
 
-/// Select-like operation.
+/// Select-like operation. Returns the number of ready descriptors, which is 0 if the timeout expired first. A timeout that is not
+/// set waits indefinitely, and a zero timeout does not wait.
 extern struct io_result twz_rt_fd_select(size_t nfds, fd_set *readfds, fd_set *writefds, fd_set *exceptfds, struct option_duration timeout);
+/// Poll-like operation. Returns the number of entries with nonzero revents, which is 0 if the timeout expired first. Timeouts
+/// behave as for twz_rt_fd_select.
 extern struct io_result twz_rt_fd_poll(struct pollfd *fds, size_t nfds, struct option_duration timeout);
 
 /// Get a config value for register reg.
@@ -100,29 +200,166 @@ extern twz_error twz_rt_fd_get_config(descriptor fd, uint32_t reg, void *val, si
 /// Set a config value for register reg. Setting a register may have side effects.
 extern twz_error twz_rt_fd_set_config(descriptor fd, uint32_t reg, const void *val, size_t len);
 
+// Config registers, for twz_rt_fd_get_config and twz_rt_fd_set_config. The val argument points to a value of the type
+// given for each register, and len must be its size.
+
+/// Local address of a socket. Value: struct socket_address.
 const uint32_t IO_REGISTER_ADDR = 1;
+/// Remote address of a connected socket. Value: struct socket_address.
 const uint32_t IO_REGISTER_PEER = 2;
+/// Socket options. Value: uint32_t, a combination of SOCKET_FLAGS_* values.
 const uint32_t IO_REGISTER_SOCKET_FLAGS = 3;
+/// How long closing a socket waits to send remaining data, or unset to not wait. Value: struct option_duration.
 const uint32_t IO_REGISTER_LINGER = 4;
+/// Time-to-live of packets sent by a socket. Value: uint32_t.
 const uint32_t IO_REGISTER_TTL = 5;
+/// Default timeout for reads, or unset to wait indefinitely. Value: struct option_duration.
 const uint32_t IO_REGISTER_READTIMEOUT = 6;
+/// Default timeout for writes, or unset to wait indefinitely. Value: struct option_duration.
 const uint32_t IO_REGISTER_WRITETIMEOUT = 7;
 
+/// Status of the descriptor. Value: uint64_t, a combination of STATUS_FLAG_* values.
 const uint32_t IO_REGISTER_STATUS = 8;
+/// Signals pending on the descriptor. Value: uint64_t, a combination of SIGNAL_FLAG_* values. Setting the register
+/// clears the signals in the value, acknowledging them. Wait for signals with the WAIT_SIGNAL waitpoint.
 const uint32_t IO_REGISTER_SIGNAL = 9;
 
+/// Terminal settings of a pty. Value: struct tty_termios.
 const uint32_t IO_REGISTER_TERMIOS = 10;
 
+/// Join or leave an IPv4 multicast group. Write-only. Value: struct multicast_membership.
 const uint32_t IO_REGISTER_MULTICAST_V4 = 11;
+/// Join or leave an IPv6 multicast group. Write-only. Value: struct multicast_membership.
 const uint32_t IO_REGISTER_MULTICAST_V6 = 12;
+/// Time-to-live of IPv4 multicast packets sent by a socket. Value: uint32_t.
 const uint32_t IO_REGISTER_MULTICAST_TTL_V4 = 13;
+/// Default flags for I/O operations, combined with the flags in each io_ctx. Value: io_flags.
 const uint32_t IO_REGISTER_IO_FLAGS = 14;
 
+/// Window size of a pty. Setting it raises SIGNAL_FLAG_WINSIZE on the pty's descriptors. Value: struct tty_winsize.
 const uint32_t IO_REGISTER_WINSIZE = 15;
 
+/// Foreground compartment of a pty, which receives the SIGNAL_FLAG_INTERRUPT, SIGNAL_FLAG_QUIT, and SIGNAL_FLAG_SUSPEND
+/// signals generated by input, on its client descriptors. Value: objid, or 0 for none.
+const uint32_t IO_REGISTER_FOREGROUND = 16;
+
+/// Value of IO_REGISTER_MULTICAST_V4 and IO_REGISTER_MULTICAST_V6.
+struct multicast_membership {
+  /// The multicast group address.
+  struct socket_address group;
+  /// For IPv4, the address of the local interface, or the unspecified address for any. For IPv6, only scope_id is used,
+  /// as the interface index, or 0 for any.
+  struct socket_address interface;
+  /// Join the group if true, leave it if false.
+  _Bool join;
+};
+
+/// The other end of the descriptor has gone away. For a pty, this is set on the client descriptors when the server side
+/// is closed or hangs up, after which reads return 0 bytes and writes fail with RESET. Setting this flag on a pty server
+/// descriptor hangs up the pty.
 const uint64_t STATUS_FLAG_TERMINATED = (1ull << 32);
 const uint64_t STATUS_FLAG_READY = (1ull << 33);
 
+/// The window size of a pty changed.
+const uint64_t SIGNAL_FLAG_WINSIZE = 1;
+/// The INTR character was input to a pty with TTY_LFLAG_ISIG set.
+const uint64_t SIGNAL_FLAG_INTERRUPT = 2;
+/// The QUIT character was input to a pty with TTY_LFLAG_ISIG set.
+const uint64_t SIGNAL_FLAG_QUIT = 4;
+/// The SUSP character was input to a pty with TTY_LFLAG_ISIG set.
+const uint64_t SIGNAL_FLAG_SUSPEND = 8;
+
+typedef uint32_t tty_flags;
+
+/// Translate carriage return to newline on input.
+const tty_flags TTY_IFLAG_ICRNL = 1;
+/// Translate newline to carriage return on input.
+const tty_flags TTY_IFLAG_INLCR = 2;
+/// Ignore carriage return on input.
+const tty_flags TTY_IFLAG_IGNCR = 4;
+/// Enable XON/XOFF flow control on output.
+const tty_flags TTY_IFLAG_IXON = 8;
+/// Strip the eighth bit of input characters.
+const tty_flags TTY_IFLAG_ISTRIP = 0x10;
+/// Signal an interrupt on break.
+const tty_flags TTY_IFLAG_BRKINT = 0x20;
+/// Enable input parity checking.
+const tty_flags TTY_IFLAG_INPCK = 0x40;
+
+/// Enable output processing.
+const tty_flags TTY_OFLAG_OPOST = 1;
+/// Translate newline to carriage return and newline on output.
+const tty_flags TTY_OFLAG_ONLCR = 2;
+
+/// Echo input characters.
+const tty_flags TTY_LFLAG_ECHO = 1;
+/// Echo newline, even if ECHO is not set.
+const tty_flags TTY_LFLAG_ECHONL = 2;
+/// Canonical mode: input is made available a line at a time, with line editing.
+const tty_flags TTY_LFLAG_ICANON = 4;
+/// Generate signals for the INTR, QUIT, and SUSP characters.
+const tty_flags TTY_LFLAG_ISIG = 8;
+/// Enable extended input processing, such as the LNEXT character.
+const tty_flags TTY_LFLAG_IEXTEN = 0x10;
+/// In canonical mode, the ERASE character erases the preceding character.
+const tty_flags TTY_LFLAG_ECHOE = 0x20;
+/// In canonical mode, the KILL character erases the current line.
+const tty_flags TTY_LFLAG_ECHOK = 0x40;
+
+// Indices into tty_termios.cc.
+
+/// Interrupt character. With TTY_LFLAG_ISIG, input of it raises SIGNAL_FLAG_INTERRUPT.
+const uint32_t TTY_CC_VINTR = 0;
+/// Quit character. With TTY_LFLAG_ISIG, input of it raises SIGNAL_FLAG_QUIT.
+const uint32_t TTY_CC_VQUIT = 1;
+/// In canonical mode, the character that erases the preceding character.
+const uint32_t TTY_CC_VERASE = 2;
+/// In canonical mode, the character that erases the current line.
+const uint32_t TTY_CC_VKILL = 3;
+/// In canonical mode, the end-of-file character. It makes the current line available without a newline, so on an empty line, a
+/// read returns 0.
+const uint32_t TTY_CC_VEOF = 4;
+/// In non-canonical mode, the read timeout in tenths of a second.
+const uint32_t TTY_CC_VTIME = 5;
+/// In non-canonical mode, the minimum number of bytes for a read to return.
+const uint32_t TTY_CC_VMIN = 6;
+/// Suspend character. With TTY_LFLAG_ISIG, input of it raises SIGNAL_FLAG_SUSPEND.
+const uint32_t TTY_CC_VSUSP = 7;
+/// In canonical mode with TTY_LFLAG_IEXTEN, the character that erases the preceding word.
+const uint32_t TTY_CC_VWERASE = 8;
+/// With TTY_LFLAG_IEXTEN, the character that makes the next input character lose any special meaning.
+const uint32_t TTY_CC_VLNEXT = 9;
+
+#define TTY_NCCS 16
+
+/// Terminal settings, the value of IO_REGISTER_TERMIOS.
+struct tty_termios {
+  /// Input flags, TTY_IFLAG_*.
+  tty_flags iflag;
+  /// Output flags, TTY_OFLAG_*.
+  tty_flags oflag;
+  /// Local flags, TTY_LFLAG_*.
+  tty_flags lflag;
+  /// Control characters, indexed by TTY_CC_*.
+  uint8_t cc[TTY_NCCS];
+  /// Input speed, in baud.
+  uint32_t ispeed;
+  /// Output speed, in baud.
+  uint32_t ospeed;
+};
+
+/// Terminal window size, the value of IO_REGISTER_WINSIZE.
+struct tty_winsize {
+  /// Height, in characters.
+  uint16_t rows;
+  /// Width, in characters.
+  uint16_t cols;
+  /// Width, in pixels, or 0 if unknown.
+  uint16_t xpixel;
+  /// Height, in pixels, or 0 if unknown.
+  uint16_t ypixel;
+};
+
 const uint32_t SOCKET_FLAGS_NODELAY = 1;
 const uint32_t SOCKET_FLAGS_ONLYV6 = 2;
 const uint32_t SOCKET_FLAGS_BROADCAST = 4;
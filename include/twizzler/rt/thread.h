@@ -20,6 +20,30 @@ extern twz_error twz_rt_futex_wake(_Atomic futex_word *ptr, int64_t max);
 /// Wake all threads instead of a maximum number
 const int64_t FUTEX_WAKE_ALL = -1;
 
+/// Width of a word waited on by twz_rt_futex_waitv.
+typedef uint32_t futex_width;
+
+/// A 32-bit word.
+const futex_width FUTEX_WIDTH_32 = 4;
+/// A 64-bit word.
+const futex_width FUTEX_WIDTH_64 = 8;
+
+/// A word to wait on with twz_rt_futex_waitv.
+struct futex_waitv_word {
+  /// Pointer to the word, aligned to its width.
+  void *ptr;
+  /// Value the word is expected to hold. For 32-bit words, only the low 32 bits are used.
+  uint64_t expected;
+  /// Width of the word, see FUTEX_WIDTH_*.
+  futex_width width;
+};
+
+/// Wait until any of the words is woken, optionally timing out. As with twz_rt_futex_wait, the thread is enqueued on all the words
+/// before they are compared to their expected values, and if any word differs, the call returns immediately. On success, val is
+/// the index of a word that differed or was woken. Returns TIMED_OUT if the timeout expires first, and INVALID_ARGUMENT if any word
+/// has an invalid width or is misaligned. Wakeups may be spurious, so callers should recheck their condition.
+extern struct io_result twz_rt_futex_waitv(const struct futex_waitv_word *words, size_t nr_words, struct option_duration timeout);
+
 /// Yield the thread now.
 extern void twz_rt_yield_now(void);
 /// Set the name of the calling thread. Must be a C string.
@@ -0,0 +1,519 @@
+//! Futures for file descriptor I/O, and a minimal single-threaded executor to run them.
+//!
+//! Each future performs its operation with non-blocking semantics. If the operation would block,
+//! the future registers the descriptor and the current value of its waitpoint (see
+//! [twz_rt_fd_waitpoint]) with a process-wide reactor and returns pending. An executor with
+//! nothing to run looks up the registered descriptors' waitpoints, sleeps on all of them at once
+//! with [twz_rt_futex_waitv], and wakes the tasks whose waitpoints changed. The reactor never
+//! holds waitpoint pointers between waits, so a leaked future can't leave it pointing at the
+//! waitpoint of a closed descriptor; a closed descriptor's task is woken instead. During a wait,
+//! the executor holds a duplicate of each descriptor, so the words it sleeps on stay valid even if
+//! the descriptor is closed by another thread.
+
+extern crate alloc;
+
+use alloc::{boxed::Box, sync::Arc, task::Wake, vec::Vec};
+use core::{
+    cell::UnsafeCell,
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use crate::{
+    error::{GenericError, TwzError},
+    fd::{OwnedFd, ProtKind, RawFd, SocketAddress},
+    io::{
+        twz_rt_fd_pread, twz_rt_fd_pread_from, twz_rt_fd_pwrite, twz_rt_fd_pwrite_to,
+        twz_rt_fd_waitpoint, Endpoint, IoCtx,
+    },
+    thread::{twz_rt_futex_wait, twz_rt_futex_waitv, twz_rt_futex_wake, FutexWaitWord},
+    Result,
+};
+
+struct Pending {
+    key: u64,
+    fd: RawFd,
+    kind: crate::bindings::wait_kind,
+    val: u64,
+    waker: Waker,
+}
+
+/// Waitpoints that pending futures are waiting on.
+struct Reactor {
+    lock: Lock,
+    next_key: AtomicU64,
+    pending: UnsafeCell<Vec<Pending>>,
+}
+
+// Safety: pending is only accessed with lock held.
+unsafe impl Sync for Reactor {}
+
+static REACTOR: Reactor = Reactor {
+    lock: Lock::new(),
+    next_key: AtomicU64::new(0),
+    pending: UnsafeCell::new(Vec::new()),
+};
+
+impl Reactor {
+    fn with<R>(&self, f: impl FnOnce(&mut Vec<Pending>) -> R) -> R {
+        self.lock.lock();
+        // Safety: we hold the lock.
+        let ret = f(unsafe { &mut *self.pending.get() });
+        self.lock.unlock();
+        ret
+    }
+
+    /// Wake waker once the waitpoint of kind on fd no longer holds val. Returns a key for
+    /// [Reactor::deregister].
+    fn register(&self, fd: RawFd, kind: crate::bindings::wait_kind, val: u64, waker: Waker) -> u64 {
+        let key = self.next_key.fetch_add(1, Ordering::Relaxed);
+        self.with(|pending| {
+            pending.push(Pending {
+                key,
+                fd,
+                kind,
+                val,
+                waker,
+            })
+        });
+        key
+    }
+
+    fn deregister(&self, key: Option<u64>) {
+        if let Some(key) = key {
+            let removed = self.with(|pending| {
+                let idx = pending.iter().position(|p| p.key == key)?;
+                Some(pending.swap_remove(idx))
+            });
+            // Dropped after unlocking, since dropping a waker may run arbitrary code.
+            drop(removed);
+        }
+    }
+
+    /// Returns true if the waitpoint of kind on fd no longer holds val, or can't be checked.
+    fn changed(fd: RawFd, kind: crate::bindings::wait_kind, val: u64) -> bool {
+        twz_rt_fd_waitpoint(fd, kind).map_or(true, |(_, now, _)| now != val)
+    }
+
+    /// Sleep until a registered waitpoint or notify changes, then wake the futures whose
+    /// waitpoints changed.
+    fn park(&self, notify: &AtomicU32, notify_val: u32) {
+        let mut regs = Vec::new();
+        self.with(|pending| regs.extend(pending.iter().map(|p| (p.key, p.fd, p.kind, p.val))));
+
+        let mut changed = Vec::new();
+        let mut words = Vec::new();
+        // Duplicates of the registered descriptors, which share their waitpoints, held open until
+        // the wait is over.
+        let mut dups = Vec::new();
+        words.push(FutexWaitWord::new32(notify, notify_val));
+        for &(key, fd, kind, val) in &regs {
+            let Ok(dup) = crate::fd::twz_rt_fd_dup(fd).map(OwnedFd::from_raw) else {
+                changed.push(key);
+                continue;
+            };
+            match twz_rt_fd_waitpoint(dup.as_raw(), kind) {
+                // Safety: the word is valid while dup is open, which outlives words.
+                Ok((point, now, _)) if now == val => {
+                    words.push(unsafe { FutexWaitWord::from_ptr64(point, val) })
+                }
+                _ => changed.push(key),
+            }
+            dups.push(dup);
+        }
+        if changed.is_empty() {
+            if words.len() == 1 {
+                // Nothing else can wake us.
+                let _ = twz_rt_futex_wait(notify, notify_val, None);
+                return;
+            }
+            let _ = twz_rt_futex_waitv(&words, None);
+            drop(words);
+            drop(dups);
+            changed.extend(
+                regs.iter()
+                    .filter(|(_, fd, kind, val)| Self::changed(*fd, *kind, *val))
+                    .map(|(key, ..)| *key),
+            );
+        }
+        changed.sort_unstable();
+
+        let woken = self.with(|pending| {
+            let mut woken = Vec::new();
+            let mut i = 0;
+            while i < pending.len() {
+                if changed.binary_search(&pending[i].key).is_ok() {
+                    woken.push(pending.swap_remove(i));
+                } else {
+                    i += 1;
+                }
+            }
+            woken
+        });
+        // Woken after unlocking, since waking may run arbitrary code.
+        for p in woken {
+            p.waker.wake();
+        }
+    }
+}
+
+/// A mutex for the reactor, which sleeps on a futex when contended rather than spinning.
+struct Lock {
+    // 0: unlocked, 1: locked, 2: locked with possible waiters.
+    state: AtomicU32,
+}
+
+impl Lock {
+    const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(0),
+        }
+    }
+
+    fn lock(&self) {
+        if self
+            .state
+            .compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            return;
+        }
+        while self.state.swap(2, Ordering::Acquire) != 0 {
+            let _ = twz_rt_futex_wait(&self.state, 2, None);
+        }
+    }
+
+    fn unlock(&self) {
+        if self.state.swap(0, Ordering::Release) == 2 {
+            let _ = twz_rt_futex_wake(&self.state, Some(1));
+        }
+    }
+}
+
+/// Run op, and if it would block, arrange for cx to be woken when the waitpoint of kind on fd
+/// changes. key holds this caller's reactor registration, if any.
+fn poll_io<T>(
+    fd: RawFd,
+    kind: crate::bindings::wait_kind,
+    key: &mut Option<u64>,
+    cx: &mut Context<'_>,
+    mut op: impl FnMut() -> Result<T>,
+) -> Poll<Result<T>> {
+    REACTOR.deregister(key.take());
+    match op() {
+        Err(TwzError::Generic(GenericError::WouldBlock)) => {}
+        res => return Poll::Ready(res),
+    }
+    let (_, val, ready) = match twz_rt_fd_waitpoint(fd, kind) {
+        Ok(wp) => wp,
+        Err(e) => return Poll::Ready(Err(e)),
+    };
+    if ready {
+        // Became ready since we tried. Retry on the next poll, after giving other tasks a turn.
+        cx.waker().wake_by_ref();
+    } else {
+        *key = Some(REACTOR.register(fd, kind, val, cx.waker().clone()));
+    }
+    Poll::Pending
+}
+
+/// A future for one I/O operation on a descriptor.
+struct IoFuture<F> {
+    fd: RawFd,
+    kind: crate::bindings::wait_kind,
+    key: Option<u64>,
+    op: F,
+}
+
+impl<T, F: FnMut() -> Result<T> + Unpin> Future for IoFuture<F> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        poll_io(this.fd, this.kind, &mut this.key, cx, &mut this.op)
+    }
+}
+
+impl<F> Drop for IoFuture<F> {
+    fn drop(&mut self) {
+        REACTOR.deregister(self.key.take());
+    }
+}
+
+fn io_future<T>(
+    fd: RawFd,
+    kind: crate::bindings::wait_kind,
+    op: impl FnMut() -> Result<T> + Unpin,
+) -> impl Future<Output = Result<T>> {
+    IoFuture {
+        fd,
+        kind,
+        key: None,
+        op,
+    }
+}
+
+/// Read from fd into buf, as [twz_rt_fd_pread], waiting asynchronously for data.
+pub fn pread<'a>(
+    fd: &'a OwnedFd,
+    buf: &'a mut [u8],
+    ctx: IoCtx,
+) -> impl Future<Output = Result<usize>> + 'a {
+    let mut ctx = ctx.nonblocking(true);
+    io_future(fd.as_raw(), crate::bindings::WAIT_READ, move || {
+        twz_rt_fd_pread(fd.as_raw(), buf, &mut ctx)
+    })
+}
+
+/// Write buf to fd, as [twz_rt_fd_pwrite], waiting asynchronously for room.
+pub fn pwrite<'a>(
+    fd: &'a OwnedFd,
+    buf: &'a [u8],
+    ctx: IoCtx,
+) -> impl Future<Output = Result<usize>> + 'a {
+    let mut ctx = ctx.nonblocking(true);
+    io_future(fd.as_raw(), crate::bindings::WAIT_WRITE, move || {
+        twz_rt_fd_pwrite(fd.as_raw(), buf, &mut ctx)
+    })
+}
+
+/// Read from fd into buf, as [twz_rt_fd_pread_from], waiting asynchronously for data.
+pub fn pread_from<'a>(
+    fd: &'a OwnedFd,
+    buf: &'a mut [u8],
+    ctx: IoCtx,
+) -> impl Future<Output = Result<(usize, Endpoint)>> + 'a {
+    let mut ctx = ctx.nonblocking(true);
+    io_future(fd.as_raw(), crate::bindings::WAIT_READ, move || {
+        twz_rt_fd_pread_from(fd.as_raw(), buf, &mut ctx)
+    })
+}
+
+/// Write buf to ep through fd, as [twz_rt_fd_pwrite_to], waiting asynchronously for room.
+pub fn pwrite_to<'a>(
+    fd: &'a OwnedFd,
+    buf: &'a [u8],
+    ctx: IoCtx,
+    ep: Endpoint,
+) -> impl Future<Output = Result<usize>> + 'a {
+    let mut ctx = ctx.nonblocking(true);
+    io_future(fd.as_raw(), crate::bindings::WAIT_WRITE, move || {
+        twz_rt_fd_pwrite_to(fd.as_raw(), buf, &mut ctx, ep)
+    })
+}
+
+/// Accept a connection on the listening socket fd, as
+/// [twz_rt_fd_open_socket_accept](crate::fd::twz_rt_fd_open_socket_accept), waiting
+/// asynchronously for one to arrive.
+pub fn accept(fd: &OwnedFd, flags: u32) -> impl Future<Output = Result<OwnedFd>> + '_ {
+    let flags = flags | crate::bindings::OPEN_FLAG_NONBLOCKING;
+    io_future(fd.as_raw(), crate::bindings::WAIT_READ, move || {
+        crate::fd::twz_rt_fd_open_socket_accept(fd.as_raw(), flags).map(OwnedFd::from_raw)
+    })
+}
+
+/// Connect a socket to addr, as
+/// [twz_rt_fd_open_socket_connect](crate::fd::twz_rt_fd_open_socket_connect), waiting
+/// asynchronously for the connection to complete. A failed connection is reported by the first
+/// I/O on the returned descriptor.
+pub async fn connect(addr: SocketAddress, flags: u32, prot: ProtKind) -> Result<OwnedFd> {
+    let fd = OwnedFd::from_raw(crate::fd::twz_rt_fd_open_socket_connect(
+        addr,
+        flags | crate::bindings::OPEN_FLAG_NONBLOCKING,
+        prot,
+    )?);
+    io_future(
+        fd.as_raw(),
+        crate::bindings::WAIT_WRITE,
+        || match twz_rt_fd_waitpoint(fd.as_raw(), crate::bindings::WAIT_WRITE)? {
+            (_, _, true) => Ok(()),
+            _ => Err(GenericError::WouldBlock.into()),
+        },
+    )
+    .await?;
+    Ok(fd)
+}
+
+/// An owned descriptor with poll-based reads and writes, in the shape of `AsyncRead` and
+/// `AsyncWrite`.
+#[derive(Debug)]
+pub struct AsyncFd {
+    fd: OwnedFd,
+    read_key: Option<u64>,
+    write_key: Option<u64>,
+}
+
+impl AsyncFd {
+    /// Wrap fd for asynchronous I/O.
+    pub fn new(fd: OwnedFd) -> Self {
+        Self {
+            fd,
+            read_key: None,
+            write_key: None,
+        }
+    }
+
+    /// Get the wrapped descriptor.
+    pub fn fd(&self) -> &OwnedFd {
+        &self.fd
+    }
+
+    /// Read into buf at the descriptor's position, or arrange for cx to be woken when the
+    /// descriptor may be readable.
+    pub fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<usize>> {
+        let fd = self.fd.as_raw();
+        poll_io(
+            fd,
+            crate::bindings::WAIT_READ,
+            &mut self.read_key,
+            cx,
+            || twz_rt_fd_pread(fd, buf, &mut IoCtx::default().nonblocking(true)),
+        )
+    }
+
+    /// Write buf at the descriptor's position, or arrange for cx to be woken when the
+    /// descriptor may be writable.
+    pub fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        let fd = self.fd.as_raw();
+        poll_io(
+            fd,
+            crate::bindings::WAIT_WRITE,
+            &mut self.write_key,
+            cx,
+            || twz_rt_fd_pwrite(fd, buf, &mut IoCtx::default().nonblocking(true)),
+        )
+    }
+
+    /// Read into buf at the descriptor's position.
+    pub async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        core::future::poll_fn(|cx| self.poll_read(cx, buf)).await
+    }
+
+    /// Write buf at the descriptor's position.
+    pub async fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        core::future::poll_fn(|cx| self.poll_write(cx, buf)).await
+    }
+
+    /// Unwrap the descriptor.
+    pub fn into_inner(mut self) -> OwnedFd {
+        REACTOR.deregister(self.read_key.take());
+        REACTOR.deregister(self.write_key.take());
+        let this = core::mem::ManuallyDrop::new(self);
+        // Safety: this is never dropped or used again, and the keys are plain data.
+        unsafe { core::ptr::read(&this.fd) }
+    }
+}
+
+impl Drop for AsyncFd {
+    fn drop(&mut self) {
+        REACTOR.deregister(self.read_key.take());
+        REACTOR.deregister(self.write_key.take());
+    }
+}
+
+/// Wakes a task by flagging it and bumping its executor's notify word.
+struct TaskWaker {
+    woken: AtomicBool,
+    notify: Arc<AtomicU32>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::Release);
+        self.notify.fetch_add(1, Ordering::Release);
+        let _ = twz_rt_futex_wake(&self.notify, None);
+    }
+}
+
+struct Task {
+    future: Pin<Box<dyn Future<Output = ()>>>,
+    waker: Arc<TaskWaker>,
+}
+
+/// A minimal single-threaded executor. Tasks are polled on the thread that calls
+/// [Executor::run], and may be woken from any thread.
+pub struct Executor {
+    tasks: Vec<Task>,
+    notify: Arc<AtomicU32>,
+}
+
+impl Default for Executor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Executor {
+    /// Make a new executor with no tasks.
+    pub fn new() -> Self {
+        Self {
+            tasks: Vec::new(),
+            notify: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    /// Add a task, to be run by [Executor::run].
+    pub fn spawn(&mut self, future: impl Future<Output = ()> + 'static) {
+        self.tasks.push(Task {
+            future: Box::pin(future),
+            waker: Arc::new(TaskWaker {
+                woken: AtomicBool::new(true),
+                notify: self.notify.clone(),
+            }),
+        });
+    }
+
+    /// Run tasks until all have completed.
+    pub fn run(&mut self) {
+        while !self.tasks.is_empty() {
+            let notify_val = self.notify.load(Ordering::Acquire);
+            let mut polled = false;
+            let mut i = 0;
+            while i < self.tasks.len() {
+                let task = &mut self.tasks[i];
+                if !task.waker.woken.swap(false, Ordering::AcqRel) {
+                    i += 1;
+                    continue;
+                }
+                polled = true;
+                let waker = Waker::from(task.waker.clone());
+                match task.future.as_mut().poll(&mut Context::from_waker(&waker)) {
+                    Poll::Ready(()) => {
+                        self.tasks.swap_remove(i);
+                    }
+                    Poll::Pending => i += 1,
+                }
+            }
+            if !polled {
+                REACTOR.park(&self.notify, notify_val);
+            }
+        }
+    }
+}
+
+/// Run future to completion on the calling thread.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = core::pin::pin!(future);
+    let notify = Arc::new(AtomicU32::new(0));
+    let task = Arc::new(TaskWaker {
+        woken: AtomicBool::new(true),
+        notify: notify.clone(),
+    });
+    let waker = Waker::from(task.clone());
+    loop {
+        let notify_val = notify.load(Ordering::Acquire);
+        if task.woken.swap(false, Ordering::AcqRel) {
+            if let Poll::Ready(out) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+                return out;
+            }
+        } else {
+            REACTOR.park(&notify, notify_val);
+        }
+    }
+}
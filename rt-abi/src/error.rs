@@ -95,6 +95,7 @@ impl TwzError {
     pub const NOT_FOUND: Self = Self::Naming(NamingError::NotFound);
     pub const SUCCESS: Self = Self::Uncategorized(bindings::SUCCESS);
     pub const BAD_HANDLE: Self = Self::Argument(ArgumentError::BadHandle);
+    pub const BUFFER_TOO_SMALL: Self = Self::Resource(ResourceError::BufferTooSmall);
 
     pub fn category(&self) -> ErrorCategory {
         match self {
@@ -210,6 +211,10 @@ impl GenericError {
             bindings::WOULD_BLOCK => TwzError::Generic(GenericError::WouldBlock),
             bindings::TIMED_OUT => TwzError::Generic(GenericError::TimedOut),
             bindings::NO_SUCH_OPERATION => TwzError::Generic(GenericError::NoSuchOperation),
+            bindings::ACCESS_DENIED => TwzError::Generic(GenericError::AccessDenied),
+            bindings::INTERRUPTED => TwzError::Generic(GenericError::Interrupted),
+            bindings::IN_PROGRESS => TwzError::Generic(GenericError::InProgress),
+            bindings::OTHER_ERROR => TwzError::Generic(GenericError::Other),
             _ => TwzError::Uncategorized(code),
         }
     }
@@ -299,6 +304,7 @@ pub enum ResourceError {
     NotConnected = bindings::NOT_CONNECTED,
     Unreachable = bindings::UNREACHABLE,
     NonAtomic = bindings::NON_ATOMIC,
+    BufferTooSmall = bindings::BUFFER_TOO_SMALL,
 }
 
 impl ResourceError {
@@ -308,6 +314,12 @@ impl ResourceError {
             bindings::OUT_OF_RESOURCES => TwzError::Resource(ResourceError::OutOfResources),
             bindings::OUT_OF_NAMES => TwzError::Resource(ResourceError::OutOfNames),
             bindings::UNAVAILABLE => TwzError::Resource(ResourceError::Unavailable),
+            bindings::REFUSED => TwzError::Resource(ResourceError::Refused),
+            bindings::BUSY => TwzError::Resource(ResourceError::Busy),
+            bindings::NOT_CONNECTED => TwzError::Resource(ResourceError::NotConnected),
+            bindings::UNREACHABLE => TwzError::Resource(ResourceError::Unreachable),
+            bindings::NON_ATOMIC => TwzError::Resource(ResourceError::NonAtomic),
+            bindings::BUFFER_TOO_SMALL => TwzError::Resource(ResourceError::BufferTooSmall),
             _ => TwzError::Uncategorized(code),
         }
     }
@@ -329,6 +341,7 @@ impl Display for ResourceError {
             ResourceError::NotConnected => write!(f, "not connected"),
             ResourceError::Unreachable => write!(f, "unreachable"),
             ResourceError::NonAtomic => write!(f, "non-atomic"),
+            ResourceError::BufferTooSmall => write!(f, "buffer too small"),
         }
     }
 }
@@ -402,6 +415,7 @@ pub enum IoError {
     DeviceError = bindings::DEVICE_ERROR,
     SeekFailed = bindings::SEEK_FAILED,
     Reset = bindings::RESET,
+    UnexpectedEof = bindings::UNEXPECTED_EOF,
 }
 
 impl IoError {
@@ -411,6 +425,8 @@ impl IoError {
             bindings::DATA_LOSS => TwzError::Io(IoError::DataLoss),
             bindings::DEVICE_ERROR => TwzError::Io(IoError::DeviceError),
             bindings::SEEK_FAILED => TwzError::Io(IoError::SeekFailed),
+            bindings::RESET => TwzError::Io(IoError::Reset),
+            bindings::UNEXPECTED_EOF => TwzError::Io(IoError::UnexpectedEof),
             _ => TwzError::Uncategorized(code),
         }
     }
@@ -428,6 +444,7 @@ impl Display for IoError {
             IoError::DeviceError => write!(f, "device error"),
             IoError::SeekFailed => write!(f, "seek failed"),
             IoError::Reset => write!(f, "reset"),
+            IoError::UnexpectedEof => write!(f, "unexpected end of file"),
         }
     }
 }
@@ -586,7 +603,7 @@ impl From<std::io::ErrorKind> for TwzError {
             std::io::ErrorKind::ArgumentListTooLong => ArgumentError::InvalidArgument.into(),
             std::io::ErrorKind::Interrupted => GenericError::Interrupted.into(),
             std::io::ErrorKind::Unsupported => GenericError::NotSupported.into(),
-            std::io::ErrorKind::UnexpectedEof => IoError::Reset.into(),
+            std::io::ErrorKind::UnexpectedEof => IoError::UnexpectedEof.into(),
             std::io::ErrorKind::OutOfMemory => ResourceError::OutOfMemory.into(),
             std::io::ErrorKind::InProgress => GenericError::InProgress.into(),
             _ => GenericError::Other.into(),
@@ -2,12 +2,20 @@
 
 use core::time::Duration;
 
+mod path;
+pub use path::*;
+
 pub use crate::bindings::descriptor as RawFd;
 use crate::{
-    error::{ArgumentError, RawTwzError, TwzError},
+    error::{
+        ArgumentError, GenericError, IoError, NamingError, RawTwzError, ResourceError, TwzError,
+    },
     nk, Result,
 };
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 bitflags::bitflags! {
     /// Flags for file descriptors.
     #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
@@ -29,6 +37,7 @@ pub enum FdKind {
     Socket = crate::bindings::fd_kind_FdKind_Socket,
     Pipe = crate::bindings::fd_kind_FdKind_Pipe,
     Compartment = crate::bindings::fd_kind_FdKind_Compartment,
+    Watch = crate::bindings::fd_kind_FdKind_Watch,
     Other = u32::MAX,
 }
 
@@ -42,6 +51,7 @@ impl From<u32> for FdKind {
             crate::bindings::fd_kind_FdKind_Socket => Self::Socket,
             crate::bindings::fd_kind_FdKind_Pipe => Self::Pipe,
             crate::bindings::fd_kind_FdKind_Compartment => Self::Compartment,
+            crate::bindings::fd_kind_FdKind_Watch => Self::Watch,
             _ => Self::Other,
         }
     }
@@ -57,11 +67,69 @@ impl Into<u32> for FdKind {
             Self::Socket => crate::bindings::fd_kind_FdKind_Socket,
             Self::Pipe => crate::bindings::fd_kind_FdKind_Pipe,
             Self::Compartment => crate::bindings::fd_kind_FdKind_Compartment,
+            Self::Watch => crate::bindings::fd_kind_FdKind_Watch,
             Self::Other => u32::MAX,
         }
     }
 }
 
+bitflags::bitflags! {
+    /// Permission bits of a unix mode, as found in [FdInfo::unix_mode].
+    #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct Permissions : u32 {
+    /// Owner may read.
+    const OWNER_READ = 0o400;
+    /// Owner may write.
+    const OWNER_WRITE = 0o200;
+    /// Owner may execute.
+    const OWNER_EXEC = 0o100;
+    /// Group may read.
+    const GROUP_READ = 0o040;
+    /// Group may write.
+    const GROUP_WRITE = 0o020;
+    /// Group may execute.
+    const GROUP_EXEC = 0o010;
+    /// Others may read.
+    const OTHER_READ = 0o004;
+    /// Others may write.
+    const OTHER_WRITE = 0o002;
+    /// Others may execute.
+    const OTHER_EXEC = 0o001;
+    /// Set user ID on execution.
+    const SET_UID = 0o4000;
+    /// Set group ID on execution.
+    const SET_GID = 0o2000;
+    /// Restrict removal of names in a namespace to their owners.
+    const STICKY = 0o1000;
+}
+}
+
+impl Permissions {
+    /// Decode the permission bits of a unix mode, ignoring the file type bits.
+    pub fn from_mode(mode: u32) -> Self {
+        Self::from_bits_truncate(mode)
+    }
+
+    /// Get the permissions as unix mode bits.
+    pub fn mode(&self) -> u32 {
+        self.bits()
+    }
+
+    /// Returns true if no one may write.
+    pub fn readonly(&self) -> bool {
+        !self.intersects(Self::OWNER_WRITE | Self::GROUP_WRITE | Self::OTHER_WRITE)
+    }
+
+    /// Clear or set the write bits. Setting write access only grants it to the owner.
+    pub fn set_readonly(&mut self, readonly: bool) {
+        if readonly {
+            self.remove(Self::OWNER_WRITE | Self::GROUP_WRITE | Self::OTHER_WRITE);
+        } else {
+            self.insert(Self::OWNER_WRITE);
+        }
+    }
+}
+
 /// Information about an open file descriptor.
 #[derive(Copy, Clone, Debug, Default)]
 pub struct FdInfo {
@@ -81,6 +149,20 @@ pub struct FdInfo {
     pub modified: Duration,
     /// Unix mode
     pub unix_mode: u32,
+    /// Number of names bound to the underlying object, or 0 if unknown
+    pub nlink: u32,
+}
+
+impl FdInfo {
+    /// Get the permission bits of the unix mode.
+    pub fn permissions(&self) -> Permissions {
+        Permissions::from_mode(self.unix_mode)
+    }
+
+    /// Returns true if both descriptors refer to the same underlying object.
+    pub fn same_object(&self, other: &FdInfo) -> bool {
+        self.id != 0 && self.id == other.id
+    }
 }
 
 impl From<crate::bindings::fd_info> for FdInfo {
@@ -94,6 +176,7 @@ impl From<crate::bindings::fd_info> for FdInfo {
             accessed: value.accessed.into(),
             modified: value.modified.into(),
             unix_mode: value.unix_mode,
+            nlink: value.nlink,
         }
     }
 }
@@ -109,6 +192,7 @@ impl From<FdInfo> for crate::bindings::fd_info {
             accessed: value.accessed.into(),
             modified: value.modified.into(),
             unix_mode: value.unix_mode,
+            nlink: value.nlink,
         }
     }
 }
@@ -189,6 +273,84 @@ impl NameEntry {
     }
 }
 
+bitflags::bitflags! {
+    /// Events reported by a watch descriptor.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct WatchMask : crate::bindings::watch_mask {
+    /// A name was created in the watched namespace.
+    const CREATED = crate::bindings::WATCH_CREATED;
+    /// A name was removed from the watched namespace, or the watched name itself was removed.
+    const REMOVED = crate::bindings::WATCH_REMOVED;
+    /// A name was renamed.
+    const RENAMED = crate::bindings::WATCH_RENAMED;
+    /// File data was modified.
+    const MODIFIED = crate::bindings::WATCH_MODIFIED;
+    /// Entry metadata (times, mode, extended attributes) changed.
+    const ATTRIB = crate::bindings::WATCH_ATTRIB;
+    /// Events were lost because the queue overflowed. Always reported.
+    const OVERFLOW = crate::bindings::WATCH_OVERFLOW;
+}
+}
+
+pub use crate::bindings::watch_event as WatchEvent;
+
+impl WatchEvent {
+    /// The event that occurred.
+    pub fn kind(&self) -> WatchMask {
+        WatchMask::from_bits_truncate(self.kind)
+    }
+
+    /// Kind of the entry the event refers to.
+    pub fn entry_kind(&self) -> FdKind {
+        FdKind::from(self.entry_kind)
+    }
+
+    /// Name of the entry, relative to the watched namespace. Empty if the event refers to the
+    /// watched name itself.
+    pub fn name_bytes(&self) -> &[u8] {
+        &self.name[0..self.name_len as usize]
+    }
+
+    /// For rename events, the old name of the entry.
+    pub fn old_name_bytes(&self) -> &[u8] {
+        &self.name[self.name_len as usize..(self.name_len + self.old_name_len) as usize]
+    }
+}
+
+/// Open a watch descriptor that reports the events in mask for a namespace or file.
+pub fn twz_rt_fd_open_watch(name: &str, mask: WatchMask, flags: u32) -> Result<RawFd> {
+    let name_len = name.len().min(crate::bindings::NAME_DATA_MAX);
+    let mut binding = crate::bindings::watch_bind_info {
+        mask: mask.bits(),
+        len: name_len,
+        name: [0; _],
+    };
+    binding.name[0..name_len].copy_from_slice(&name.as_bytes()[0..name_len]);
+    unsafe {
+        nk!(crate::bindings::twz_rt_fd_open(
+            OpenKind::Watch.into(),
+            flags,
+            ((&mut binding) as *mut crate::bindings::watch_bind_info).cast(),
+            core::mem::size_of::<crate::bindings::watch_bind_info>(),
+        ))
+        .into()
+    }
+}
+
+/// Read events from a watch descriptor. Returns the number of events read, which may be fewer
+/// than requested. Waits for at least one event unless ctx is nonblocking.
+pub fn twz_rt_fd_read_watch(
+    fd: RawFd,
+    events: &mut [WatchEvent],
+    ctx: &mut crate::io::IoCtx,
+) -> Result<usize> {
+    let bytes = unsafe {
+        core::slice::from_raw_parts_mut(events.as_mut_ptr().cast::<u8>(), size_of_val(events))
+    };
+    let len = crate::io::twz_rt_fd_pread(fd, bytes, ctx)?;
+    Ok(len / size_of::<WatchEvent>())
+}
+
 /// Enumerate sub-names for an fd (e.g. directory entries). Returns Some(n) on success, None if no
 /// names can be enumerated. Return of Some(n) indicates number of items read into the buffer, 0 if
 /// end of name list. Offset argument specifies number of entries to skip.
@@ -275,20 +437,21 @@ pub fn twz_rt_fd_read_binds(binds: &mut [binding_info]) -> usize {
     }
 }
 
-/// Open a file descriptor by name, as a C-string.
-pub fn twz_rt_fd_copen(
-    name: &core::ffi::CStr,
+fn open_path(
+    context: crate::bindings::name_context,
+    name: &[u8],
     create: crate::bindings::create_options,
     flags: u32,
 ) -> Result<RawFd> {
-    let name_len = name.count_bytes().min(crate::bindings::NAME_DATA_MAX);
+    let name_len = name.len().min(crate::bindings::NAME_DATA_MAX);
     let mut info = crate::bindings::open_info {
         len: name_len,
         create,
         flags,
+        context,
         name: [0; _],
     };
-    info.name[0..name_len].copy_from_slice(&name.to_bytes()[0..name_len]);
+    info.name[0..name_len].copy_from_slice(&name[0..name_len]);
     unsafe {
         nk!(crate::bindings::twz_rt_fd_open(
             crate::bindings::open_kind_OpenKind_Path,
@@ -300,29 +463,32 @@ pub fn twz_rt_fd_copen(
     }
 }
 
+/// Open a file descriptor by name, as a C-string.
+pub fn twz_rt_fd_copen(
+    name: &core::ffi::CStr,
+    create: crate::bindings::create_options,
+    flags: u32,
+) -> Result<RawFd> {
+    open_path(
+        crate::bindings::NAME_CONTEXT_CURRENT,
+        name.to_bytes(),
+        create,
+        flags,
+    )
+}
+
 /// Open a file descriptor by name, as a Rust-string.
 pub fn twz_rt_fd_open(
     name: &str,
     create: crate::bindings::create_options,
     flags: u32,
 ) -> Result<RawFd> {
-    let name_len = name.as_bytes().len().min(crate::bindings::NAME_DATA_MAX);
-    let mut info = crate::bindings::open_info {
-        len: name_len,
+    open_path(
+        crate::bindings::NAME_CONTEXT_CURRENT,
+        name.as_bytes(),
         create,
         flags,
-        name: [0; _],
-    };
-    info.name[0..name_len].copy_from_slice(&name.as_bytes()[0..name_len]);
-    unsafe {
-        nk!(crate::bindings::twz_rt_fd_open(
-            crate::bindings::open_kind_OpenKind_Path,
-            flags,
-            (&mut info as *mut crate::bindings::open_info).cast(),
-            size_of_val(&info),
-        )
-        .into())
-    }
+    )
 }
 
 /// Remove a name
@@ -360,6 +526,53 @@ pub fn twz_rt_fd_symlink(name: &str, target: &str) -> Result<()> {
     }
 }
 
+/// Bind a new name to the object that old_name is bound to.
+pub fn twz_rt_fd_link(old_name: &str, new_name: &str) -> Result<()> {
+    unsafe {
+        RawTwzError::new(nk!(crate::bindings::twz_rt_fd_link(
+            old_name.as_ptr().cast(),
+            old_name.len(),
+            new_name.as_ptr().cast(),
+            new_name.len(),
+        )))
+        .result()
+    }
+}
+
+/// Create a new object that is not bound to any name, opened for reading and writing. The
+/// object will be stored with the namespace `namespace`, and can be made visible once its
+/// contents are complete with [link_into]. If the descriptor is closed before then, the object
+/// is deleted.
+pub fn open_anonymous(namespace: &str, flags: u32) -> Result<OwnedFd> {
+    twz_rt_fd_open(
+        namespace,
+        crate::bindings::create_options {
+            id: 0,
+            kind: crate::bindings::CREATE_KIND_ANONYMOUS,
+        },
+        flags | crate::bindings::OPEN_FLAG_READ | crate::bindings::OPEN_FLAG_WRITE,
+    )
+    .map(OwnedFd::from_raw)
+}
+
+/// Atomically bind `name` to the object behind `fd`, typically one opened with
+/// [open_anonymous]. The object's data is synced first, so readers of `name` see either no
+/// file or its complete contents. Fails with
+/// [NamingError::AlreadyExists](crate::error::NamingError::AlreadyExists) if the name is
+/// taken.
+pub fn link_into(fd: RawFd, name: &str) -> Result<()> {
+    let info = twz_rt_fd_get_info(fd)?;
+    if info.id == 0 {
+        return Err(GenericError::NotSupported.into());
+    }
+    sync_checked(fd)?;
+    let create = crate::bindings::create_options {
+        id: info.id,
+        kind: crate::bindings::CREATE_KIND_NEW,
+    };
+    twz_rt_fd_open(name, create, crate::bindings::OPEN_FLAG_READ).map(twz_rt_fd_close)
+}
+
 /// Rename a name in the namespace.
 pub fn twz_rt_fd_rename(old_name: &str, new_name: &str) -> Result<()> {
     unsafe {
@@ -373,10 +586,182 @@ pub fn twz_rt_fd_rename(old_name: &str, new_name: &str) -> Result<()> {
     }
 }
 
+bitflags::bitflags! {
+    /// Flags for [twz_rt_fd_rename_with_flags].
+    #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct RenameFlags : crate::bindings::rename_flags {
+    /// Fail with [NamingError::AlreadyExists](crate::error::NamingError::AlreadyExists) instead of replacing new_name.
+    const NOREPLACE = crate::bindings::RENAME_NOREPLACE;
+    /// Swap the objects bound to old_name and new_name, which must both exist.
+    const EXCHANGE = crate::bindings::RENAME_EXCHANGE;
+}
+}
+
+/// Rename a name in the namespace. At all times, including after a crash, new_name refers to
+/// either its previous object or old_name's object. Object data is not synced, so sync it
+/// first if new_name must never refer to incomplete data, as [atomic_write] does.
+pub fn twz_rt_fd_rename_with_flags(
+    old_name: &str,
+    new_name: &str,
+    flags: RenameFlags,
+) -> Result<()> {
+    unsafe {
+        RawTwzError::new(nk!(crate::bindings::twz_rt_fd_rename_with_flags(
+            old_name.as_ptr().cast(),
+            old_name.len(),
+            new_name.as_ptr().cast(),
+            new_name.len(),
+            flags.bits(),
+        )))
+        .result()
+    }
+}
+
+fn readlink_raw(name: &str, buf: &mut [u8]) -> (RawTwzError, usize) {
+    let mut len: u64 = 0;
+    let e = unsafe {
+        nk!(crate::bindings::twz_rt_fd_readlink(
+            name.as_ptr().cast(),
+            name.len(),
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+            &mut len,
+        ))
+    };
+    (RawTwzError::new(e), len as usize)
+}
+
+/// Read the target of a symlink into buf, returning its length. If buf is too small, returns
+/// [ResourceError::BufferTooSmall](crate::error::ResourceError::BufferTooSmall).
 pub fn twz_rt_fd_readlink(name: &str, buf: &mut [u8]) -> Result<usize> {
+    fixed_buffer(buf.len(), readlink_raw(name, buf))
+}
+
+/// Get the length of the target of a symlink, for sizing a buffer for [twz_rt_fd_readlink].
+pub fn twz_rt_fd_readlink_len(name: &str) -> Result<usize> {
+    required_len(readlink_raw(name, &mut []))
+}
+
+/// Read the target of a symlink into a newly allocated string.
+#[cfg(feature = "alloc")]
+pub fn twz_rt_fd_readlink_owned(name: &str) -> Result<alloc::string::String> {
+    read_owned(|buf| readlink_raw(name, buf))
+}
+
+/// Check the result of a call that fills a caller-supplied buffer and reports the length it
+/// needed.
+fn fixed_buffer(buf_len: usize, (err, len): (RawTwzError, usize)) -> Result<usize> {
+    err.result()?;
+    if len > buf_len {
+        return Err(TwzError::BUFFER_TOO_SMALL);
+    }
+    Ok(len)
+}
+
+/// Get the length that a call filling a caller-supplied buffer needed, whether or not the buffer
+/// was large enough.
+fn required_len((err, len): (RawTwzError, usize)) -> Result<usize> {
+    match err.result() {
+        Ok(()) | Err(TwzError::Resource(crate::error::ResourceError::BufferTooSmall)) => Ok(len),
+        Err(e) => Err(e),
+    }
+}
+
+/// Call f with a buffer, growing the buffer and retrying whenever f reports that the buffer was
+/// too small, and return the result as a string.
+#[cfg(feature = "alloc")]
+fn read_owned(
+    mut f: impl FnMut(&mut [u8]) -> (RawTwzError, usize),
+) -> Result<alloc::string::String> {
+    let mut buf = alloc::vec![0; crate::bindings::NAME_ENTRY_LEN as usize];
+    loop {
+        let (err, len) = f(&mut buf);
+        match err.result() {
+            Ok(()) if len <= buf.len() => {
+                buf.truncate(len);
+                return alloc::string::String::from_utf8(buf)
+                    .map_err(|_| ArgumentError::InvalidArgument.into());
+            }
+            Ok(()) | Err(TwzError::Resource(crate::error::ResourceError::BufferTooSmall)) => {
+                // Guard against a runtime that doesn't report the length it needs.
+                let new_len = len.max(buf.len() * 2);
+                buf.resize(new_len, 0);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Maximum length of an extended attribute key, in bytes.
+pub const XATTR_NAME_MAX: usize = crate::bindings::XATTR_NAME_MAX;
+/// Maximum length of an extended attribute value, in bytes.
+pub const XATTR_VALUE_MAX: usize = crate::bindings::XATTR_VALUE_MAX;
+
+bitflags::bitflags! {
+    /// Flags for setting extended attributes.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct XattrFlags : crate::bindings::xattr_flags {
+    /// Fail if the attribute already exists.
+    const CREATE = crate::bindings::XATTR_FLAG_CREATE;
+    /// Fail if the attribute does not already exist.
+    const REPLACE = crate::bindings::XATTR_FLAG_REPLACE;
+}
+}
+
+fn check_xattr_key(key: &str) -> Result<()> {
+    if key.is_empty() || key.len() > XATTR_NAME_MAX || key.as_bytes().contains(&0) {
+        return Err(ArgumentError::InvalidArgument.into());
+    }
+    Ok(())
+}
+
+/// Read the value of an extended attribute into buf. Returns the full length of the value, which
+/// may be larger than buf.
+pub fn twz_rt_fd_getxattr(name: &str, key: &str, buf: &mut [u8]) -> Result<usize> {
+    check_xattr_key(key)?;
+    let mut len: u64 = 0;
+    unsafe {
+        RawTwzError::new(nk!(crate::bindings::twz_rt_fd_getxattr(
+            name.as_ptr().cast(),
+            name.len(),
+            key.as_ptr().cast(),
+            key.len(),
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+            &mut len,
+        )))
+        .result()?;
+    }
+    Ok(len as usize)
+}
+
+/// Set an extended attribute. Keys must be non-empty, at most [XATTR_NAME_MAX] bytes, and free of
+/// NUL bytes. Values may be at most [XATTR_VALUE_MAX] bytes.
+pub fn twz_rt_fd_setxattr(name: &str, key: &str, value: &[u8], flags: XattrFlags) -> Result<()> {
+    check_xattr_key(key)?;
+    if value.len() > XATTR_VALUE_MAX {
+        return Err(ArgumentError::InvalidArgument.into());
+    }
+    unsafe {
+        RawTwzError::new(nk!(crate::bindings::twz_rt_fd_setxattr(
+            name.as_ptr().cast(),
+            name.len(),
+            key.as_ptr().cast(),
+            key.len(),
+            value.as_ptr().cast(),
+            value.len(),
+            flags.bits(),
+        )))
+        .result()
+    }
+}
+
+/// List extended attribute keys into buf, each terminated by a NUL byte. Returns the full length
+/// of the list, which may be larger than buf. See [xattr_keys] for splitting up the list.
+pub fn twz_rt_fd_listxattr(name: &str, buf: &mut [u8]) -> Result<usize> {
     let mut len: u64 = 0;
     unsafe {
-        RawTwzError::new(nk!(crate::bindings::twz_rt_fd_readlink(
+        RawTwzError::new(nk!(crate::bindings::twz_rt_fd_listxattr(
             name.as_ptr().cast(),
             name.len(),
             buf.as_mut_ptr().cast(),
@@ -388,6 +773,25 @@ pub fn twz_rt_fd_readlink(name: &str, buf: &mut [u8]) -> Result<usize> {
     Ok(len as usize)
 }
 
+/// Remove an extended attribute.
+pub fn twz_rt_fd_removexattr(name: &str, key: &str) -> Result<()> {
+    check_xattr_key(key)?;
+    unsafe {
+        RawTwzError::new(nk!(crate::bindings::twz_rt_fd_removexattr(
+            name.as_ptr().cast(),
+            name.len(),
+            key.as_ptr().cast(),
+            key.len(),
+        )))
+        .result()
+    }
+}
+
+/// Iterate over the keys in a list filled by [twz_rt_fd_listxattr].
+pub fn xattr_keys(list: &[u8]) -> impl Iterator<Item = &[u8]> {
+    list.split(|b| *b == 0).filter(|key| !key.is_empty())
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 #[repr(u32)]
 pub enum OpenKind {
@@ -401,6 +805,7 @@ pub enum OpenKind {
     PtyClient = crate::bindings::open_kind_OpenKind_PtyClient,
     Compartment = crate::bindings::open_kind_OpenKind_Compartment,
     KernelConsole = crate::bindings::open_kind_OpenKind_KernelConsole,
+    Watch = crate::bindings::open_kind_OpenKind_Watch,
 }
 
 impl TryFrom<u32> for OpenKind {
@@ -418,6 +823,7 @@ impl TryFrom<u32> for OpenKind {
             crate::bindings::open_kind_OpenKind_Object => Ok(Self::Object),
             crate::bindings::open_kind_OpenKind_Compartment => Ok(Self::Compartment),
             crate::bindings::open_kind_OpenKind_KernelConsole => Ok(Self::KernelConsole),
+            crate::bindings::open_kind_OpenKind_Watch => Ok(Self::Watch),
 
             _ => Err(()),
         }
@@ -437,6 +843,7 @@ impl From<OpenKind> for u32 {
             OpenKind::PtyClient => crate::bindings::open_kind_OpenKind_PtyClient,
             OpenKind::Compartment => crate::bindings::open_kind_OpenKind_Compartment,
             OpenKind::KernelConsole => crate::bindings::open_kind_OpenKind_KernelConsole,
+            OpenKind::Watch => crate::bindings::open_kind_OpenKind_Watch,
         }
     }
 }
@@ -754,18 +1161,23 @@ pub fn twz_rt_fd_dup(fd: RawFd) -> Result<RawFd> {
     }
 }
 
-/// Sync a file descriptor.
-pub fn twz_rt_fd_sync(fd: RawFd) {
+fn sync_checked(fd: RawFd) -> Result<()> {
     unsafe {
-        nk!(crate::bindings::twz_rt_fd_cmd(
+        RawTwzError::new(nk!(crate::bindings::twz_rt_fd_cmd(
             fd,
             crate::bindings::FD_CMD_SYNC,
             core::ptr::null_mut(),
             core::ptr::null_mut(),
-        ));
+        )))
+        .result()
     }
 }
 
+/// Sync a file descriptor.
+pub fn twz_rt_fd_sync(fd: RawFd) {
+    let _ = sync_checked(fd);
+}
+
 /// Truncate a file descriptor.
 pub fn twz_rt_fd_truncate(fd: RawFd, mut len: u64) -> Result<()> {
     unsafe {
@@ -806,64 +1218,543 @@ pub fn twz_rt_fd_shutdown(fd: RawFd, read: bool, write: bool) -> Result<()> {
     Ok(())
 }
 
-/// Close a file descriptor. If the fd is already closed, or invalid, this function has no effect.
-pub fn twz_rt_fd_close(fd: RawFd) {
-    unsafe { nk!(crate::bindings::twz_rt_fd_close(fd)) }
+/// Set the accessed and modified times of a file descriptor. Times that are None are left
+/// unchanged.
+pub fn twz_rt_fd_set_times(
+    fd: RawFd,
+    accessed: Option<Duration>,
+    modified: Option<Duration>,
+) -> Result<()> {
+    let mut times = crate::bindings::fd_times {
+        accessed: accessed.into(),
+        modified: modified.into(),
+    };
+    unsafe {
+        RawTwzError::new(nk!(crate::bindings::twz_rt_fd_cmd(
+            fd,
+            crate::bindings::FD_CMD_SET_TIMES,
+            (&mut times as *mut crate::bindings::fd_times).cast(),
+            core::ptr::null_mut(),
+        )))
+        .result()
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
-#[repr(u32)]
-pub enum NameRoot {
-    Root = crate::bindings::name_root_NameRoot_Root,
-    Home = crate::bindings::name_root_NameRoot_Home,
-    Current = crate::bindings::name_root_NameRoot_Current,
-    Temp = crate::bindings::name_root_NameRoot_Temp,
-    Exe = crate::bindings::name_root_NameRoot_Exe,
+/// Change the permission bits of a file descriptor's unix mode.
+pub fn twz_rt_fd_chmod(fd: RawFd, perms: Permissions) -> Result<()> {
+    let mut mode = perms.mode();
+    unsafe {
+        RawTwzError::new(nk!(crate::bindings::twz_rt_fd_cmd(
+            fd,
+            crate::bindings::FD_CMD_CHMOD,
+            (&mut mode as *mut u32).cast(),
+            core::ptr::null_mut(),
+        )))
+        .result()
+    }
 }
 
-impl From<u32> for NameRoot {
-    fn from(value: u32) -> Self {
-        match value {
-            crate::bindings::name_root_NameRoot_Root => NameRoot::Root,
-            crate::bindings::name_root_NameRoot_Home => NameRoot::Home,
-            crate::bindings::name_root_NameRoot_Current => NameRoot::Current,
-            crate::bindings::name_root_NameRoot_Temp => NameRoot::Temp,
-            crate::bindings::name_root_NameRoot_Exe => NameRoot::Exe,
-            _ => panic!("invalid NameRoot value"),
-        }
-    }
+bitflags::bitflags! {
+    /// Flags for [twz_rt_fd_allocate].
+    #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct AllocateFlags : crate::bindings::allocate_flags {
+    /// Deallocate the range, leaving a hole that reads as zeros. Never changes the file length.
+    const PUNCH_HOLE = crate::bindings::ALLOCATE_FLAG_PUNCH_HOLE;
+    /// Do not extend the file length when allocating past the end of the file.
+    const KEEP_SIZE = crate::bindings::ALLOCATE_FLAG_KEEP_SIZE;
+}
 }
 
-pub fn twz_rt_get_nameroot(root: NameRoot, buf: &mut [u8]) -> Result<usize> {
+/// Allocate or, with [AllocateFlags::PUNCH_HOLE], deallocate storage for len bytes of a file
+/// descriptor starting at offset, like fallocate.
+pub fn twz_rt_fd_allocate(fd: RawFd, offset: u64, len: u64, flags: AllocateFlags) -> Result<()> {
+    let mut arg = crate::bindings::fd_allocate {
+        offset,
+        len,
+        flags: flags.bits(),
+    };
     unsafe {
-        nk!(
-            crate::bindings::twz_rt_get_nameroot(root as u32, buf.as_mut_ptr().cast(), buf.len())
-                .into()
-        )
+        RawTwzError::new(nk!(crate::bindings::twz_rt_fd_cmd(
+            fd,
+            crate::bindings::FD_CMD_ALLOCATE,
+            (&mut arg as *mut crate::bindings::fd_allocate).cast(),
+            core::ptr::null_mut(),
+        )))
+        .result()
     }
 }
 
-pub fn twz_rt_set_nameroot(root: NameRoot, buf: &[u8]) -> Result<()> {
-    let res = unsafe {
-        nk!(crate::bindings::twz_rt_set_nameroot(
-            root as u32,
-            buf.as_ptr().cast(),
-            buf.len()
-        ))
-    };
-    let r = RawTwzError::new(res);
-    if r.is_success() {
-        Ok(())
-    } else {
-        Err(r.error())
-    }
+/// Deallocate len bytes of a file descriptor starting at offset, so they read as zeros and use
+/// no storage.
+pub fn twz_rt_fd_punch_hole(fd: RawFd, offset: u64, len: u64) -> Result<()> {
+    twz_rt_fd_allocate(fd, offset, len, AllocateFlags::PUNCH_HOLE)
 }
 
-#[derive(Default, Copy, Clone)]
+/// Kinds of advisory locks.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 #[repr(u32)]
-pub enum NameResolver {
-    #[default]
-    Default = crate::bindings::name_resolver_NameResolver_Default,
+pub enum LockKind {
+    /// Many holders may share the lock, but not with an exclusive holder.
+    Shared = crate::bindings::lock_kind_LockKind_Shared,
+    /// Only one holder may hold the lock.
+    Exclusive = crate::bindings::lock_kind_LockKind_Exclusive,
+}
+
+/// A request for an advisory lock on a byte range of a descriptor. By default, the request is
+/// blocking and covers the whole file.
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct LockRequest(crate::bindings::fd_lock);
+
+impl LockRequest {
+    pub fn new(kind: LockKind) -> Self {
+        Self(crate::bindings::fd_lock {
+            kind: kind as u32,
+            flags: 0,
+            start: 0,
+            len: 0,
+            timeout: None::<Duration>.into(),
+        })
+    }
+
+    /// Lock len bytes starting at start. A len of 0 extends the range to the end of the file.
+    pub fn range(mut self, start: u64, len: u64) -> Self {
+        self.0.start = start;
+        self.0.len = len;
+        self
+    }
+
+    /// If val is true, fail with [ResourceError::Busy](crate::error::ResourceError::Busy) instead
+    /// of waiting for a conflicting lock to be released.
+    pub fn nonblocking(mut self, val: bool) -> Self {
+        if val {
+            self.0.flags |= crate::bindings::LOCK_FLAG_NONBLOCKING;
+        } else {
+            self.0.flags &= !crate::bindings::LOCK_FLAG_NONBLOCKING;
+        }
+        self
+    }
+
+    /// Give up waiting for the lock after timeout. Ignored for nonblocking requests.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.0.timeout = timeout.into();
+        self
+    }
+}
+
+/// Acquire an advisory lock on a file descriptor.
+pub fn twz_rt_fd_lock(fd: RawFd, req: &LockRequest) -> Result<()> {
+    let mut lock = req.0;
+    unsafe {
+        RawTwzError::new(nk!(crate::bindings::twz_rt_fd_cmd(
+            fd,
+            crate::bindings::FD_CMD_LOCK,
+            (&mut lock as *mut crate::bindings::fd_lock).cast(),
+            core::ptr::null_mut(),
+        )))
+        .result()
+    }
+}
+
+/// Release an advisory lock on the byte range given by req.
+pub fn twz_rt_fd_unlock(fd: RawFd, req: &LockRequest) -> Result<()> {
+    let mut lock = req.0;
+    unsafe {
+        RawTwzError::new(nk!(crate::bindings::twz_rt_fd_cmd(
+            fd,
+            crate::bindings::FD_CMD_UNLOCK,
+            (&mut lock as *mut crate::bindings::fd_lock).cast(),
+            core::ptr::null_mut(),
+        )))
+        .result()
+    }
+}
+
+/// An advisory lock held on a file descriptor, released when dropped. The descriptor must stay
+/// open for as long as the lock is held.
+pub struct FileLock {
+    fd: RawFd,
+    req: LockRequest,
+}
+
+impl FileLock {
+    /// Acquire the lock described by req on fd.
+    pub fn new(fd: RawFd, req: LockRequest) -> Result<Self> {
+        twz_rt_fd_lock(fd, &req)?;
+        Ok(Self { fd, req })
+    }
+
+    /// Release the lock, reporting any error.
+    pub fn unlock(self) -> Result<()> {
+        let this = core::mem::ManuallyDrop::new(self);
+        twz_rt_fd_unlock(this.fd, &this.req)
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = twz_rt_fd_unlock(self.fd, &self.req);
+    }
+}
+
+/// Close a file descriptor. If the fd is already closed, or invalid, this function has no effect.
+pub fn twz_rt_fd_close(fd: RawFd) {
+    unsafe { nk!(crate::bindings::twz_rt_fd_close(fd)) }
+}
+
+/// An owned file descriptor, closed on drop.
+#[derive(Debug)]
+pub struct OwnedFd(RawFd);
+
+impl OwnedFd {
+    /// Take ownership of a raw file descriptor.
+    pub fn from_raw(fd: RawFd) -> Self {
+        Self(fd)
+    }
+
+    /// Get the raw file descriptor, which remains owned by this object.
+    pub fn as_raw(&self) -> RawFd {
+        self.0
+    }
+
+    /// Release ownership of the raw file descriptor without closing it.
+    pub fn into_raw(self) -> RawFd {
+        let this = core::mem::ManuallyDrop::new(self);
+        this.0
+    }
+
+    /// Map the object underlying this descriptor. Fails with
+    /// [GenericError::NotSupported](crate::error::GenericError::NotSupported) if the descriptor
+    /// is not backed by an object.
+    pub fn map(&self, flags: crate::object::MapFlags) -> Result<crate::object::ObjectHandle> {
+        let info = twz_rt_fd_get_info(self.0)?;
+        if info.id == 0 {
+            return Err(GenericError::NotSupported.into());
+        }
+        crate::object::twz_rt_map_object(crate::object::ObjID::new(info.id), flags)
+    }
+}
+
+impl Drop for OwnedFd {
+    fn drop(&mut self) {
+        twz_rt_fd_close(self.0);
+    }
+}
+
+/// A file's data mapped into memory, for reading it without copying. The mapping covers the
+/// file's length as of creation or the last call to [FileMapping::sync_len].
+///
+/// Writes through other descriptors or mappings are visible, as with mmap, so the data can change
+/// at any time. It is accessed through raw pointers or the copies of [FileMapping::read_at] and
+/// [FileMapping::write_at], or through the slices of [FileMapping::as_slice] and
+/// [FileMapping::as_mut_slice] when the caller can rule out concurrent writers.
+pub struct FileMapping<'a> {
+    fd: &'a OwnedFd,
+    handle: crate::object::ObjectHandle,
+    len: usize,
+}
+
+impl<'a> FileMapping<'a> {
+    /// Map the data of the file open at fd.
+    pub fn new(fd: &'a OwnedFd, flags: crate::object::MapFlags) -> Result<Self> {
+        let mut this = Self {
+            fd,
+            handle: fd.map(flags)?,
+            len: 0,
+        };
+        this.sync_len()?;
+        Ok(this)
+    }
+
+    /// Get the object handle of the mapping.
+    pub fn handle(&self) -> &crate::object::ObjectHandle {
+        &self.handle
+    }
+
+    /// The length of the mapped data.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the mapped data is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get a pointer to the mapped data, which is valid for len() bytes while self lives. The
+    /// file's data starts at the start of the object's data, as described for fd_info.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.handle.start()
+    }
+
+    /// Get a mutable pointer to the mapped data. Writing through it is only allowed if the
+    /// mapping was made with [MapFlags::WRITE](crate::object::MapFlags::WRITE).
+    pub fn as_mut_ptr(&self) -> *mut u8 {
+        self.handle.start()
+    }
+
+    /// Get the mapped data as a slice.
+    ///
+    /// # Safety
+    /// While the returned slice lives, the file's data must not be written, through this mapping,
+    /// another mapping, or any descriptor, including ones held by other threads or compartments.
+    pub unsafe fn as_slice(&self) -> &[u8] {
+        core::slice::from_raw_parts(self.as_ptr(), self.len)
+    }
+
+    /// Get the mapped data as a mutable slice.
+    ///
+    /// # Safety
+    /// The mapping must have been made with [MapFlags::WRITE](crate::object::MapFlags::WRITE).
+    /// While the returned slice lives, the file's data must not be read or written through another
+    /// mapping or any descriptor, including ones held by other threads or compartments.
+    pub unsafe fn as_mut_slice(&mut self) -> &mut [u8] {
+        core::slice::from_raw_parts_mut(self.as_mut_ptr(), self.len)
+    }
+
+    /// Copy the mapped data starting at off into buf, returning the number of bytes copied, which
+    /// is less than buf.len() if the mapped data ends first.
+    pub fn read_at(&self, off: usize, buf: &mut [u8]) -> usize {
+        let count = buf.len().min(self.len.saturating_sub(off));
+        // Safety: off + count is within the mapped length, and buf is not part of the mapping.
+        unsafe { core::ptr::copy_nonoverlapping(self.as_ptr().add(off), buf.as_mut_ptr(), count) };
+        count
+    }
+
+    /// Copy buf into the mapped data starting at off, returning the number of bytes copied, which
+    /// is less than buf.len() if the mapped data ends first. Fails with
+    /// [GenericError::AccessDenied](crate::error::GenericError::AccessDenied) if the mapping is
+    /// not writable. Use [FileMapping::truncate] to extend the file first.
+    pub fn write_at(&self, off: usize, buf: &[u8]) -> Result<usize> {
+        if !self
+            .handle
+            .map_flags()
+            .contains(crate::object::MapFlags::WRITE)
+        {
+            return Err(GenericError::AccessDenied.into());
+        }
+        let count = buf.len().min(self.len.saturating_sub(off));
+        // Safety: off + count is within the mapped length, the mapping is writable, and buf is not
+        // part of the mapping.
+        unsafe { core::ptr::copy_nonoverlapping(buf.as_ptr(), self.as_mut_ptr().add(off), count) };
+        Ok(count)
+    }
+
+    /// Update the mapped length to the file's current length, after the file is truncated or
+    /// extended.
+    pub fn sync_len(&mut self) -> Result<()> {
+        let info = twz_rt_fd_get_info(self.fd.as_raw())?;
+        self.len = usize::try_from(info.size)
+            .unwrap_or(usize::MAX)
+            .min(self.handle.valid_len());
+        Ok(())
+    }
+
+    /// Truncate the file to len, and update the mapped length.
+    pub fn truncate(&mut self, len: u64) -> Result<()> {
+        twz_rt_fd_truncate(self.fd.as_raw(), len)?;
+        self.sync_len()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
+#[repr(u32)]
+pub enum NameRoot {
+    Root = crate::bindings::name_root_NameRoot_Root,
+    Home = crate::bindings::name_root_NameRoot_Home,
+    Current = crate::bindings::name_root_NameRoot_Current,
+    Temp = crate::bindings::name_root_NameRoot_Temp,
+    Exe = crate::bindings::name_root_NameRoot_Exe,
+}
+
+impl From<u32> for NameRoot {
+    fn from(value: u32) -> Self {
+        match value {
+            crate::bindings::name_root_NameRoot_Root => NameRoot::Root,
+            crate::bindings::name_root_NameRoot_Home => NameRoot::Home,
+            crate::bindings::name_root_NameRoot_Current => NameRoot::Current,
+            crate::bindings::name_root_NameRoot_Temp => NameRoot::Temp,
+            crate::bindings::name_root_NameRoot_Exe => NameRoot::Exe,
+            _ => panic!("invalid NameRoot value"),
+        }
+    }
+}
+
+fn get_nameroot_raw(root: NameRoot, buf: &mut [u8]) -> (RawTwzError, usize) {
+    let res = unsafe {
+        nk!(crate::bindings::twz_rt_get_nameroot(
+            root as u32,
+            buf.as_mut_ptr().cast(),
+            buf.len()
+        ))
+    };
+    (RawTwzError::new(res.err), res.val)
+}
+
+/// Read the name of a name root into buf, returning its length. If buf is too small, returns
+/// [ResourceError::BufferTooSmall](crate::error::ResourceError::BufferTooSmall).
+pub fn twz_rt_get_nameroot(root: NameRoot, buf: &mut [u8]) -> Result<usize> {
+    fixed_buffer(buf.len(), get_nameroot_raw(root, buf))
+}
+
+/// Get the length of the name of a name root, for sizing a buffer for [twz_rt_get_nameroot].
+pub fn twz_rt_get_nameroot_len(root: NameRoot) -> Result<usize> {
+    required_len(get_nameroot_raw(root, &mut []))
+}
+
+/// Read the name of a name root into a newly allocated string.
+#[cfg(feature = "alloc")]
+pub fn twz_rt_get_nameroot_owned(root: NameRoot) -> Result<alloc::string::String> {
+    read_owned(|buf| get_nameroot_raw(root, buf))
+}
+
+pub fn twz_rt_set_nameroot(root: NameRoot, buf: &[u8]) -> Result<()> {
+    let res = unsafe {
+        nk!(crate::bindings::twz_rt_set_nameroot(
+            root as u32,
+            buf.as_ptr().cast(),
+            buf.len()
+        ))
+    };
+    let r = RawTwzError::new(res);
+    if r.is_success() {
+        Ok(())
+    } else {
+        Err(r.error())
+    }
+}
+
+/// A set of name roots to resolve names against, independent of the process-wide roots set by
+/// [twz_rt_set_nameroot]. A context can be used explicitly, with [NameContext::open] and
+/// [NameContext::resolve_name], or installed as the calling thread's override with
+/// [NameContext::enter]. The context is destroyed on drop.
+pub struct NameContext(crate::bindings::name_context);
+
+impl NameContext {
+    /// Create a new context, starting with a copy of the calling thread's name roots.
+    pub fn new() -> Result<Self> {
+        let res: Result<u32> = unsafe { nk!(crate::bindings::twz_rt_name_context_create().into()) };
+        res.map(Self)
+    }
+
+    /// Get the raw name context handle.
+    pub fn raw(&self) -> crate::bindings::name_context {
+        self.0
+    }
+
+    /// Set a name root of this context.
+    pub fn set_root(&self, root: NameRoot, path: &[u8]) -> Result<()> {
+        unsafe {
+            RawTwzError::new(nk!(crate::bindings::twz_rt_name_context_set_root(
+                self.0,
+                root as u32,
+                path.as_ptr().cast(),
+                path.len()
+            )))
+            .result()
+        }
+    }
+
+    fn get_root_raw(&self, root: NameRoot, buf: &mut [u8]) -> (RawTwzError, usize) {
+        let res = unsafe {
+            nk!(crate::bindings::twz_rt_name_context_get_root(
+                self.0,
+                root as u32,
+                buf.as_mut_ptr().cast(),
+                buf.len()
+            ))
+        };
+        (RawTwzError::new(res.err), res.val)
+    }
+
+    /// Get a name root of this context into buf, returning its length. If buf is too small,
+    /// returns [ResourceError::BufferTooSmall](crate::error::ResourceError::BufferTooSmall).
+    pub fn get_root(&self, root: NameRoot, buf: &mut [u8]) -> Result<usize> {
+        fixed_buffer(buf.len(), self.get_root_raw(root, buf))
+    }
+
+    /// Get the length of a name root of this context, for sizing a buffer for
+    /// [NameContext::get_root].
+    pub fn get_root_len(&self, root: NameRoot) -> Result<usize> {
+        required_len(self.get_root_raw(root, &mut []))
+    }
+
+    /// Get a name root of this context as a newly allocated string.
+    #[cfg(feature = "alloc")]
+    pub fn get_root_owned(&self, root: NameRoot) -> Result<alloc::string::String> {
+        read_owned(|buf| self.get_root_raw(root, buf))
+    }
+
+    /// Open a file descriptor by name, resolved against this context.
+    pub fn open(
+        &self,
+        name: &str,
+        create: crate::bindings::create_options,
+        flags: u32,
+    ) -> Result<RawFd> {
+        open_path(self.0, name.as_bytes(), create, flags)
+    }
+
+    /// Resolve a name against this context.
+    pub fn resolve_name(
+        &self,
+        resolver: NameResolver,
+        name: impl AsRef<str>,
+    ) -> Result<crate::object::ObjID> {
+        let name = name.as_ref().as_bytes();
+        let res = unsafe {
+            nk!(crate::bindings::twz_rt_resolve_name_in(
+                self.0,
+                resolver as u32,
+                name.as_ptr().cast(),
+                name.len()
+            ))
+        };
+        let r = RawTwzError::new(res.err);
+        if r.is_success() {
+            Ok(res.val.into())
+        } else {
+            Err(r.error())
+        }
+    }
+
+    /// Make this context the calling thread's name context until the returned guard is dropped,
+    /// at which point the previous override (if any) is restored.
+    pub fn enter(&self) -> NameContextGuard<'_> {
+        let prev = unsafe { nk!(crate::bindings::twz_rt_set_thread_name_context(self.0)) };
+        NameContextGuard {
+            prev,
+            _ctx: core::marker::PhantomData,
+        }
+    }
+}
+
+impl Drop for NameContext {
+    fn drop(&mut self) {
+        unsafe { nk!(crate::bindings::twz_rt_name_context_destroy(self.0)) }
+    }
+}
+
+/// Restores the calling thread's previous name context override on drop. See
+/// [NameContext::enter].
+pub struct NameContextGuard<'a> {
+    prev: crate::bindings::name_context,
+    _ctx: core::marker::PhantomData<&'a NameContext>,
+}
+
+impl Drop for NameContextGuard<'_> {
+    fn drop(&mut self) {
+        unsafe { nk!(crate::bindings::twz_rt_set_thread_name_context(self.prev)) };
+    }
+}
+
+impl !Send for NameContextGuard<'_> {}
+
+#[derive(Default, Copy, Clone)]
+#[repr(u32)]
+pub enum NameResolver {
+    #[default]
+    Default = crate::bindings::name_resolver_NameResolver_Default,
     Socket = crate::bindings::name_resolver_NameResolver_Socket,
 }
 
@@ -897,28 +1788,44 @@ pub fn twz_rt_resolve_name(
     }
 }
 
-pub fn twz_rt_canon_name(
-    resolver: NameResolver,
-    name: impl AsRef<str>,
-    out_name: &mut [u8],
-) -> Result<usize> {
-    let name = name.as_ref().as_bytes();
-    let mut out_len = out_name.len();
+fn canon_name_raw(resolver: NameResolver, name: &[u8], out: &mut [u8]) -> (RawTwzError, usize) {
+    let mut out_len = out.len();
     let res = unsafe {
         nk!(crate::bindings::twz_rt_canon_name(
             resolver as u32,
             name.as_ptr().cast(),
             name.len(),
-            out_name.as_mut_ptr().cast(),
+            out.as_mut_ptr().cast(),
             &mut out_len,
         ))
     };
-    let r = RawTwzError::new(res);
-    if r.is_success() {
-        Ok(out_len)
-    } else {
-        Err(r.error())
-    }
+    (RawTwzError::new(res), out_len)
+}
+
+/// Get the canonical form of a name into out_name, returning its length. If out_name is too
+/// small, returns [ResourceError::BufferTooSmall](crate::error::ResourceError::BufferTooSmall).
+pub fn twz_rt_canon_name(
+    resolver: NameResolver,
+    name: impl AsRef<str>,
+    out_name: &mut [u8],
+) -> Result<usize> {
+    let res = canon_name_raw(resolver, name.as_ref().as_bytes(), out_name);
+    fixed_buffer(out_name.len(), res)
+}
+
+/// Get the length of the canonical form of a name, for sizing a buffer for [twz_rt_canon_name].
+pub fn twz_rt_canon_name_len(resolver: NameResolver, name: impl AsRef<str>) -> Result<usize> {
+    required_len(canon_name_raw(resolver, name.as_ref().as_bytes(), &mut []))
+}
+
+/// Get the canonical form of a name as a newly allocated string.
+#[cfg(feature = "alloc")]
+pub fn twz_rt_canon_name_owned(
+    resolver: NameResolver,
+    name: impl AsRef<str>,
+) -> Result<alloc::string::String> {
+    let name = name.as_ref().as_bytes();
+    read_owned(|buf| canon_name_raw(resolver, name, buf))
 }
 
 pub fn twz_rt_socket_names(
@@ -943,3 +1850,638 @@ pub fn twz_rt_socket_names(
         Err(r.error())
     }
 }
+
+fn open_existing(name: &str, flags: u32) -> Result<RawFd> {
+    twz_rt_fd_open(
+        name,
+        crate::bindings::create_options {
+            id: 0,
+            kind: crate::bindings::CREATE_KIND_EXISTING,
+        },
+        flags,
+    )
+}
+
+/// A fixed-size buffer for building up names while traversing a namespace.
+struct NameBuf {
+    buf: [u8; crate::bindings::NAME_DATA_MAX],
+    len: usize,
+}
+
+impl NameBuf {
+    fn new(name: &str) -> Result<Self> {
+        let mut this = Self {
+            buf: [0; crate::bindings::NAME_DATA_MAX],
+            len: 0,
+        };
+        this.append(name.as_bytes())?;
+        Ok(this)
+    }
+
+    fn append(&mut self, bytes: &[u8]) -> Result<()> {
+        if self.len + bytes.len() > self.buf.len() {
+            return Err(ArgumentError::InvalidArgument.into());
+        }
+        self.buf[self.len..(self.len + bytes.len())].copy_from_slice(bytes);
+        self.len += bytes.len();
+        Ok(())
+    }
+
+    /// Append a path component, returning the previous length for a later call to truncate.
+    fn push(&mut self, component: &[u8]) -> Result<usize> {
+        core::str::from_utf8(component).map_err(|_| ArgumentError::InvalidArgument)?;
+        let old = self.len;
+        if self.len > 0 && self.buf[self.len - 1] != b'/' {
+            self.append(b"/")?;
+        }
+        if let Err(e) = self.append(component) {
+            self.len = old;
+            return Err(e);
+        }
+        Ok(old)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    fn as_str(&self) -> &str {
+        // Safety: only &str and UTF-8 validated components are appended.
+        unsafe { core::str::from_utf8_unchecked(&self.buf[0..self.len]) }
+    }
+}
+
+/// Maximum number of symlinks [walk] will follow along any one path before reporting
+/// [NamingError::LinkLoop](crate::error::NamingError::LinkLoop).
+pub const WALK_MAX_LINKS: usize = 32;
+
+/// Maximum number of levels [walk] and [remove_tree] will descend below the root before failing
+/// with [ResourceError::OutOfResources](crate::error::ResourceError::OutOfResources). Each level
+/// is a stack frame holding a batch of name entries, so this bounds the stack used.
+pub const WALK_MAX_DEPTH: usize = 64;
+
+const WALK_BATCH: usize = 8;
+
+/// Order in which [walk] visits a namespace relative to its children.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum WalkOrder {
+    /// Visit a namespace before any of its children.
+    #[default]
+    Pre,
+    /// Visit a namespace after all of its children.
+    Post,
+}
+
+/// Options controlling a namespace traversal.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct WalkOptions {
+    max_depth: Option<usize>,
+    follow_symlinks: bool,
+    order: WalkOrder,
+}
+
+impl WalkOptions {
+    /// Do not descend more than `depth` levels below the root. The root has depth 0.
+    pub fn max_depth(mut self, depth: Option<usize>) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Descend into namespaces that are reached through symlinks.
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Set the order in which namespaces are visited relative to their children.
+    pub fn order(mut self, order: WalkOrder) -> Self {
+        self.order = order;
+        self
+    }
+}
+
+/// An entry visited during a namespace traversal.
+#[derive(Debug, Copy, Clone)]
+pub struct WalkEntry<'a> {
+    /// Full name of the entry, starting with the root passed to [walk].
+    pub name: &'a str,
+    /// Number of levels below the root. The root has depth 0.
+    pub depth: usize,
+    /// Information about the entry. For a followed symlink, this describes the target.
+    pub info: FdInfo,
+    /// Target of the entry, if it is a symlink.
+    pub link_target: Option<&'a [u8]>,
+}
+
+/// Namespaces on the path from the root to the current entry, used to detect symlink loops.
+struct WalkAncestor<'a> {
+    id: twizzler_types::ObjID,
+    parent: Option<&'a WalkAncestor<'a>>,
+}
+
+impl WalkAncestor<'_> {
+    fn contains(&self, id: twizzler_types::ObjID) -> bool {
+        self.id == id || self.parent.is_some_and(|p| p.contains(id))
+    }
+}
+
+/// Recursively visit `root` and every name below it, calling `f` for each entry. If `f` returns
+/// an error, the traversal stops and the error is returned. Following a symlink to a namespace
+/// that is already being traversed, or following more than [WALK_MAX_LINKS] symlinks along one
+/// path, fails with [NamingError::LinkLoop](crate::error::NamingError::LinkLoop). Descending
+/// more than [WALK_MAX_DEPTH] levels, unless limited by [WalkOptions::max_depth], fails with
+/// [ResourceError::OutOfResources](crate::error::ResourceError::OutOfResources).
+pub fn walk(
+    root: &str,
+    options: WalkOptions,
+    mut f: impl FnMut(&WalkEntry<'_>) -> Result<()>,
+) -> Result<()> {
+    let mut name = NameBuf::new(root)?;
+    let fd = OwnedFd::from_raw(open_existing(root, crate::bindings::OPEN_FLAG_READ)?);
+    let info = twz_rt_fd_get_info(fd.as_raw())?;
+    drop(fd);
+    walk_inner(&mut name, 0, info, None, 0, None, &options, &mut f)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn walk_inner(
+    name: &mut NameBuf,
+    depth: usize,
+    info: FdInfo,
+    link_target: Option<&[u8]>,
+    links: usize,
+    parent: Option<&WalkAncestor<'_>>,
+    options: &WalkOptions,
+    f: &mut impl FnMut(&WalkEntry<'_>) -> Result<()>,
+) -> Result<()> {
+    if info.kind != FdKind::Directory {
+        return f(&WalkEntry {
+            name: name.as_str(),
+            depth,
+            info,
+            link_target,
+        });
+    }
+
+    if options.order == WalkOrder::Pre {
+        f(&WalkEntry {
+            name: name.as_str(),
+            depth,
+            info,
+            link_target,
+        })?;
+    }
+
+    if options.max_depth.is_none_or(|max| depth < max) {
+        if depth >= WALK_MAX_DEPTH {
+            return Err(ResourceError::OutOfResources.into());
+        }
+        let this = WalkAncestor {
+            id: info.id,
+            parent,
+        };
+        let fd = OwnedFd::from_raw(open_existing(
+            name.as_str(),
+            crate::bindings::OPEN_FLAG_READ,
+        )?);
+        let mut entries = [NameEntry::default(); WALK_BATCH];
+        let mut off = 0;
+        loop {
+            let count = twz_rt_fd_enumerate_names(fd.as_raw(), &mut entries, off)?;
+            if count == 0 {
+                break;
+            }
+            off += count;
+            for entry in &entries[0..count] {
+                let old = name.push(entry.name_bytes())?;
+                let mut child_info = FdInfo::from(entry.info);
+                let mut child_links = links;
+                let child_target = if child_info.kind == FdKind::SymLink {
+                    Some(entry.linkname_bytes())
+                } else {
+                    None
+                };
+
+                if child_info.kind == FdKind::SymLink && options.follow_symlinks {
+                    child_links += 1;
+                    if child_links > WALK_MAX_LINKS {
+                        return Err(NamingError::LinkLoop.into());
+                    }
+                    // A dangling symlink is visited as a symlink, not an error.
+                    if let Ok(target) =
+                        open_existing(name.as_str(), crate::bindings::OPEN_FLAG_READ)
+                    {
+                        let target = OwnedFd::from_raw(target);
+                        child_info = twz_rt_fd_get_info(target.as_raw())?;
+                    }
+                    if child_info.kind == FdKind::Directory
+                        && child_info.id != 0
+                        && this.contains(child_info.id)
+                    {
+                        return Err(NamingError::LinkLoop.into());
+                    }
+                }
+
+                walk_inner(
+                    name,
+                    depth + 1,
+                    child_info,
+                    child_target,
+                    child_links,
+                    Some(&this),
+                    options,
+                    f,
+                )?;
+                name.truncate(old);
+            }
+        }
+    }
+
+    if options.order == WalkOrder::Post {
+        f(&WalkEntry {
+            name: name.as_str(),
+            depth,
+            info,
+            link_target,
+        })?;
+    }
+    Ok(())
+}
+
+/// Remove `root` and, if it is a namespace, everything below it. Symlinks are removed, not
+/// followed. Fails like [walk] if the tree is more than [WALK_MAX_DEPTH] levels deep; names
+/// removed before the failure stay removed.
+pub fn remove_tree(root: &str) -> Result<()> {
+    let mut name = NameBuf::new(root)?;
+    let fd = OwnedFd::from_raw(open_existing(
+        root,
+        crate::bindings::OPEN_FLAG_READ | crate::bindings::OPEN_FLAG_SYMLINK,
+    )?);
+    let info = twz_rt_fd_get_info(fd.as_raw())?;
+    drop(fd);
+    remove_tree_inner(&mut name, 0, info.kind)
+}
+
+fn remove_tree_inner(name: &mut NameBuf, depth: usize, kind: FdKind) -> Result<()> {
+    if kind == FdKind::Directory {
+        if depth >= WALK_MAX_DEPTH {
+            return Err(ResourceError::OutOfResources.into());
+        }
+        let fd = OwnedFd::from_raw(open_existing(
+            name.as_str(),
+            crate::bindings::OPEN_FLAG_READ,
+        )?);
+        let mut entries = [NameEntry::default(); WALK_BATCH];
+        // Removing entries shifts the remaining ones down, so always read from the start.
+        loop {
+            let count = twz_rt_fd_enumerate_names(fd.as_raw(), &mut entries, 0)?;
+            if count == 0 {
+                break;
+            }
+            for entry in &entries[0..count] {
+                let old = name.push(entry.name_bytes())?;
+                remove_tree_inner(name, depth + 1, FdKind::from(entry.info.kind))?;
+                name.truncate(old);
+            }
+        }
+    }
+    twz_rt_fd_remove(name.as_str())
+}
+
+/// Copy the tree of names rooted at `src` to `dst`, which must not already exist. Namespaces and
+/// symlinks are recreated. If `rebind` is true, regular files under `dst` are bound to the same
+/// objects as the corresponding files under `src`, so no data is copied and later writes through
+/// either name are visible through both. Otherwise, or if the runtime cannot bind a name to an
+/// existing object, file contents are copied. A symlink whose target doesn't fit in
+/// NAME_DATA_MAX bytes fails with
+/// [ResourceError::BufferTooSmall](crate::error::ResourceError::BufferTooSmall).
+pub fn copy_tree(src: &str, dst: &str, rebind: bool) -> Result<()> {
+    let mut dst_name = NameBuf::new(dst)?;
+    walk(src, WalkOptions::default(), |entry| {
+        let old = dst_name.len;
+        let suffix = entry.name[src.len()..].trim_start_matches('/');
+        if !suffix.is_empty() {
+            dst_name.push(suffix.as_bytes())?;
+        }
+        let res = copy_entry(entry, dst_name.as_str(), rebind);
+        dst_name.truncate(old);
+        res
+    })
+}
+
+fn copy_entry(entry: &WalkEntry<'_>, dst: &str, rebind: bool) -> Result<()> {
+    match entry.info.kind {
+        FdKind::Directory => twz_rt_fd_mkns(dst),
+        FdKind::SymLink => {
+            let mut target = [0u8; crate::bindings::NAME_DATA_MAX];
+            let len = twz_rt_fd_readlink(entry.name, &mut target)?;
+            let target = core::str::from_utf8(&target[0..len])
+                .map_err(|_| ArgumentError::InvalidArgument)?;
+            twz_rt_fd_symlink(dst, target)
+        }
+        _ => {
+            if rebind && entry.info.id != 0 {
+                let create = crate::bindings::create_options {
+                    id: entry.info.id,
+                    kind: crate::bindings::CREATE_KIND_NEW,
+                };
+                match twz_rt_fd_open(dst, create, crate::bindings::OPEN_FLAG_READ) {
+                    Ok(fd) => {
+                        twz_rt_fd_close(fd);
+                        return Ok(());
+                    }
+                    Err(TwzError::Generic(GenericError::NotSupported)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            copy_file_data(entry.name, dst)
+        }
+    }
+}
+
+const COPY_CHUNK_LEN: usize = 1024 * 1024;
+
+fn copy_file_data(src: &str, dst: &str) -> Result<()> {
+    let src = OwnedFd::from_raw(open_existing(src, crate::bindings::OPEN_FLAG_READ)?);
+    let dst = OwnedFd::from_raw(twz_rt_fd_open(
+        dst,
+        crate::bindings::create_options {
+            id: 0,
+            kind: crate::bindings::CREATE_KIND_NEW,
+        },
+        crate::bindings::OPEN_FLAG_READ | crate::bindings::OPEN_FLAG_WRITE,
+    )?);
+    let mut off = 0;
+    loop {
+        let len =
+            crate::io::twz_rt_fd_copy_range(src.as_raw(), off, dst.as_raw(), off, COPY_CHUNK_LEN)?;
+        if len == 0 {
+            return Ok(());
+        }
+        off += len as u64;
+    }
+}
+
+/// Write all of buf at offset off, retrying short writes.
+fn pwrite_all(fd: RawFd, buf: &[u8], off: u64) -> Result<()> {
+    let mut written = 0;
+    while written < buf.len() {
+        let mut ctx = crate::io::IoCtx::default().offset(Some(off + written as u64));
+        match crate::io::twz_rt_fd_pwrite(fd, &buf[written..], &mut ctx)? {
+            0 => return Err(IoError::DataLoss.into()),
+            n => written += n,
+        }
+    }
+    Ok(())
+}
+
+/// Total the lengths of all non-namespace entries under `root`, like `du`. Objects reachable
+/// through more than one name are counted once per name.
+pub fn du(root: &str, options: WalkOptions) -> Result<u64> {
+    let mut total = 0;
+    walk(root, options, |entry| {
+        if entry.info.kind != FdKind::Directory {
+            total += entry.info.size;
+        }
+        Ok(())
+    })?;
+    Ok(total)
+}
+
+/// Number of random names tried by [create_temp_file], [create_temp_dir], and [atomic_write]
+/// before giving up.
+pub const TEMP_MAX_ATTEMPTS: usize = 64;
+
+const TEMP_RANDOM_BYTES: usize = 6;
+
+/// Build a random name under the Temp name root, of the form `<temp root>/<prefix><random hex>`.
+/// Fails with [ResourceError::Unavailable] if the runtime stops returning random bytes.
+fn temp_name(prefix: &str) -> Result<NameBuf> {
+    if prefix.contains('/') {
+        return Err(ArgumentError::InvalidArgument.into());
+    }
+    let mut name = NameBuf::new("")?;
+    name.len = twz_rt_get_nameroot(NameRoot::Temp, &mut name.buf)?;
+    core::str::from_utf8(&name.buf[0..name.len]).map_err(|_| ArgumentError::InvalidArgument)?;
+    name.push(prefix.as_bytes())?;
+    append_random(&mut name)?;
+    Ok(name)
+}
+
+/// Append random hex characters to name.
+fn append_random(name: &mut NameBuf) -> Result<()> {
+    let mut random = [core::mem::MaybeUninit::new(0u8); TEMP_RANDOM_BYTES];
+    let mut filled = 0;
+    while filled < random.len() {
+        let len = crate::random::twz_rt_get_random(
+            &mut random[filled..],
+            crate::random::GetRandomFlags::empty(),
+        );
+        if len == 0 {
+            return Err(ResourceError::Unavailable.into());
+        }
+        filled += len;
+    }
+    let mut suffix = [0u8; TEMP_RANDOM_BYTES * 2];
+    for (i, byte) in random.iter().enumerate() {
+        // Safety: every byte was initialized above, and filled in by get_random.
+        let byte = unsafe { byte.assume_init() };
+        suffix[i * 2] = b"0123456789abcdef"[(byte >> 4) as usize];
+        suffix[i * 2 + 1] = b"0123456789abcdef"[(byte & 0xf) as usize];
+    }
+    name.append(&suffix)
+}
+
+/// Try f with new random names from make until it doesn't report that the name exists.
+fn with_random_name<T>(
+    mut make: impl FnMut() -> Result<NameBuf>,
+    mut f: impl FnMut(&str) -> Result<T>,
+) -> Result<(NameBuf, T)> {
+    for _ in 0..TEMP_MAX_ATTEMPTS {
+        let name = make()?;
+        match f(name.as_str()) {
+            Ok(t) => return Ok((name, t)),
+            Err(TwzError::Naming(NamingError::AlreadyExists)) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(NamingError::AlreadyExists.into())
+}
+
+/// A uniquely named file under the Temp name root, created by [create_temp_file]. On drop, the
+/// descriptor is closed and the name removed.
+pub struct TempFile {
+    // Closed in drop before the name and object are removed.
+    fd: core::mem::ManuallyDrop<OwnedFd>,
+    name: NameBuf,
+    delete: bool,
+}
+
+impl TempFile {
+    /// Get the file descriptor for the file.
+    pub fn fd(&self) -> RawFd {
+        self.fd.as_raw()
+    }
+
+    /// Get the full name of the file.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// If set, also delete the underlying object on drop, rather than only removing its name.
+    pub fn set_delete_object(&mut self, delete: bool) {
+        self.delete = delete;
+    }
+
+    /// Keep the file, returning its descriptor. The name is not removed.
+    pub fn keep(self) -> OwnedFd {
+        let mut this = core::mem::ManuallyDrop::new(self);
+        // Safety: this is never dropped or used again, and name is plain data.
+        unsafe { core::mem::ManuallyDrop::take(&mut this.fd) }
+    }
+}
+
+impl core::fmt::Debug for TempFile {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TempFile")
+            .field("fd", &*self.fd)
+            .field("name", &self.name())
+            .finish()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let id = self
+            .delete
+            .then(|| twz_rt_fd_get_info(self.fd()).ok())
+            .flatten();
+        // Safety: fd is not used again.
+        unsafe { core::mem::ManuallyDrop::drop(&mut self.fd) };
+        let _ = twz_rt_fd_remove(self.name());
+        if let Some(info) = id {
+            let _ = crate::object::twz_rt_delete_object(crate::object::ObjID::new(info.id));
+        }
+    }
+}
+
+/// Create a new, uniquely named file under the Temp name root, opened for reading and writing.
+/// The name is `prefix` followed by random characters.
+pub fn create_temp_file(prefix: &str) -> Result<TempFile> {
+    let (name, fd) = with_random_name(
+        || temp_name(prefix),
+        |name| {
+            twz_rt_fd_open(
+                name,
+                crate::bindings::create_options {
+                    id: 0,
+                    kind: crate::bindings::CREATE_KIND_NEW,
+                },
+                crate::bindings::OPEN_FLAG_READ | crate::bindings::OPEN_FLAG_WRITE,
+            )
+        },
+    )?;
+    Ok(TempFile {
+        fd: core::mem::ManuallyDrop::new(OwnedFd::from_raw(fd)),
+        name,
+        delete: false,
+    })
+}
+
+/// A uniquely named namespace under the Temp name root, created by [create_temp_dir]. On drop,
+/// the namespace and everything under it is removed, as by [remove_tree].
+pub struct TempDir {
+    name: NameBuf,
+    delete: bool,
+}
+
+impl TempDir {
+    /// Get the full name of the namespace.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// If set, also delete the namespace's object on drop, rather than only removing its name.
+    /// Objects bound under the namespace are only unbound.
+    pub fn set_delete_object(&mut self, delete: bool) {
+        self.delete = delete;
+    }
+
+    /// Keep the namespace and its contents.
+    pub fn keep(self) {
+        core::mem::forget(self);
+    }
+}
+
+impl core::fmt::Debug for TempDir {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TempDir")
+            .field("name", &self.name())
+            .finish()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let id = if self.delete {
+            open_existing(self.name(), crate::bindings::OPEN_FLAG_READ)
+                .map(OwnedFd::from_raw)
+                .and_then(|fd| twz_rt_fd_get_info(fd.as_raw()))
+                .ok()
+        } else {
+            None
+        };
+        let _ = remove_tree(self.name());
+        if let Some(info) = id {
+            let _ = crate::object::twz_rt_delete_object(crate::object::ObjID::new(info.id));
+        }
+    }
+}
+
+/// Create a new, uniquely named namespace under the Temp name root. The name is `prefix`
+/// followed by random characters.
+pub fn create_temp_dir(prefix: &str) -> Result<TempDir> {
+    let (name, ()) = with_random_name(|| temp_name(prefix), twz_rt_fd_mkns)?;
+    Ok(TempDir {
+        name,
+        delete: false,
+    })
+}
+
+/// Replace the contents of the file `name` with `bytes`, creating it if needed. The data is
+/// written to a new temporary name next to `name`, synced, and renamed over `name`, so after a
+/// crash `name` holds either its old contents or all of `bytes`. On failure, the temporary name
+/// and its object are removed.
+pub fn atomic_write(name: &str, bytes: &[u8]) -> Result<()> {
+    let (tmp, fd) = with_random_name(
+        || {
+            let mut tmp = NameBuf::new(name)?;
+            tmp.append(b".tmp-")?;
+            append_random(&mut tmp)?;
+            Ok(tmp)
+        },
+        |tmp| {
+            twz_rt_fd_open(
+                tmp,
+                crate::bindings::create_options {
+                    id: 0,
+                    kind: crate::bindings::CREATE_KIND_NEW,
+                },
+                crate::bindings::OPEN_FLAG_READ | crate::bindings::OPEN_FLAG_WRITE,
+            )
+        },
+    )?;
+    let fd = OwnedFd::from_raw(fd);
+    let res = pwrite_all(fd.as_raw(), bytes, 0)
+        .and_then(|_| sync_checked(fd.as_raw()))
+        .and_then(|_| twz_rt_fd_rename_with_flags(tmp.as_str(), name, RenameFlags::empty()));
+    if res.is_err() {
+        let info = twz_rt_fd_get_info(fd.as_raw());
+        drop(fd);
+        let _ = twz_rt_fd_remove(tmp.as_str());
+        if let Ok(info) = info {
+            let _ = crate::object::twz_rt_delete_object(crate::object::ObjID::new(info.id));
+        }
+    }
+    res
+}
@@ -0,0 +1,531 @@
+//! Borrowed and owned Twizzler names, with lexical manipulation.
+//!
+//! Names are `/`-separated, and resolved from the root given by their prefix, as described for
+//! name_root in fd.h: a leading `/` for [NameRoot::Root], a first component of `~`, `~temp`, or
+//! `~exe` for [NameRoot::Home], [NameRoot::Temp], or [NameRoot::Exe], and no prefix for
+//! [NameRoot::Current].
+
+use super::NameRoot;
+
+/// A single component of a [TwzPath].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Component<'a> {
+    /// The prefix of a name, giving the root it is resolved from.
+    Prefix(NameRoot),
+    /// A `.` component.
+    CurDir,
+    /// A `..` component.
+    ParentDir,
+    /// Any other component.
+    Normal(&'a str),
+}
+
+impl<'a> Component<'a> {
+    /// Get the component as a string.
+    pub fn as_str(&self) -> &'a str {
+        match self {
+            Component::Prefix(root) => prefix_str(*root),
+            Component::CurDir => ".",
+            Component::ParentDir => "..",
+            Component::Normal(s) => s,
+        }
+    }
+}
+
+/// The prefix of names resolved from root.
+fn prefix_str(root: NameRoot) -> &'static str {
+    match root {
+        NameRoot::Root => "/",
+        NameRoot::Home => "~",
+        NameRoot::Current => "",
+        NameRoot::Temp => "~temp",
+        NameRoot::Exe => "~exe",
+    }
+}
+
+/// Split the prefix from a name, returning the root it names and the rest of the name.
+fn split_prefix(name: &str) -> Option<(NameRoot, &str)> {
+    if name.starts_with('/') {
+        return Some((NameRoot::Root, name.trim_start_matches('/')));
+    }
+    let (first, rest) = name.split_once('/').unwrap_or((name, ""));
+    let root = match first {
+        "~" => NameRoot::Home,
+        "~temp" => NameRoot::Temp,
+        "~exe" => NameRoot::Exe,
+        _ => return None,
+    };
+    Some((root, rest.trim_start_matches('/')))
+}
+
+/// Iterator over the [Component]s of a [TwzPath]. Empty components (from repeated or trailing
+/// separators) are skipped.
+#[derive(Clone)]
+pub struct Components<'a> {
+    first: bool,
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Components<'a> {
+    type Item = Component<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if core::mem::take(&mut self.first) {
+            if let Some((root, rest)) = split_prefix(self.rest) {
+                self.rest = rest;
+                return Some(Component::Prefix(root));
+            }
+        }
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+            let (comp, rest) = self.rest.split_once('/').unwrap_or((self.rest, ""));
+            self.rest = rest;
+            match comp {
+                "" => continue,
+                "." => return Some(Component::CurDir),
+                ".." => return Some(Component::ParentDir),
+                s => return Some(Component::Normal(s)),
+            }
+        }
+    }
+}
+
+/// A borrowed Twizzler name, the `str` to [TwzPathBuf]'s `String`.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct TwzPath(str);
+
+impl TwzPath {
+    /// Wrap a string as a name.
+    pub fn new<S: AsRef<str> + ?Sized>(s: &S) -> &TwzPath {
+        // Safety: TwzPath is repr(transparent) over str.
+        unsafe { &*(s.as_ref() as *const str as *const TwzPath) }
+    }
+
+    /// Get the name as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns true if the name is resolved from [NameRoot::Root].
+    pub fn is_absolute(&self) -> bool {
+        self.0.starts_with('/')
+    }
+
+    /// Returns true if the name starts with a prefix, so is not resolved from
+    /// [NameRoot::Current].
+    pub fn has_prefix(&self) -> bool {
+        split_prefix(&self.0).is_some()
+    }
+
+    /// The root this name is resolved from.
+    pub fn name_root(&self) -> NameRoot {
+        split_prefix(&self.0).map_or(NameRoot::Current, |(root, _)| root)
+    }
+
+    /// Iterate over the components of this name.
+    pub fn components(&self) -> Components<'_> {
+        Components {
+            first: true,
+            rest: &self.0,
+        }
+    }
+
+    /// The name without its final component, or None if the name is empty or only a prefix.
+    pub fn parent(&self) -> Option<&TwzPath> {
+        let s = self.0.trim_end_matches('/');
+        if s.is_empty() || split_prefix(s).is_some_and(|(_, rest)| rest.is_empty()) {
+            return None;
+        }
+        Some(match s.rfind('/') {
+            Some(idx) => {
+                let parent = s[0..idx].trim_end_matches('/');
+                if parent.is_empty() {
+                    TwzPath::new("/")
+                } else {
+                    TwzPath::new(parent)
+                }
+            }
+            None => TwzPath::new(""),
+        })
+    }
+
+    /// The final component of the name, if it is a normal component.
+    pub fn file_name(&self) -> Option<&str> {
+        match self.components().last()? {
+            Component::Normal(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The file name without its extension.
+    pub fn file_stem(&self) -> Option<&str> {
+        let name = self.file_name()?;
+        match name.rsplit_once('.') {
+            Some((stem, _)) if !stem.is_empty() => Some(stem),
+            _ => Some(name),
+        }
+    }
+
+    /// The extension of the file name, after the last `.`. A leading `.` does not start an
+    /// extension.
+    pub fn extension(&self) -> Option<&str> {
+        match self.file_name()?.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => Some(ext),
+            _ => None,
+        }
+    }
+
+    /// Returns true if base is a component-wise prefix of this name.
+    pub fn starts_with(&self, base: impl AsRef<TwzPath>) -> bool {
+        let mut this = self.components();
+        base.as_ref().components().all(|c| this.next() == Some(c))
+    }
+}
+
+impl AsRef<TwzPath> for TwzPath {
+    fn as_ref(&self) -> &TwzPath {
+        self
+    }
+}
+
+impl AsRef<TwzPath> for str {
+    fn as_ref(&self) -> &TwzPath {
+        TwzPath::new(self)
+    }
+}
+
+impl AsRef<str> for TwzPath {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl core::fmt::Display for TwzPath {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl core::fmt::Debug for TwzPath {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub use owned::TwzPathBuf;
+
+#[cfg(feature = "alloc")]
+mod owned {
+    extern crate alloc;
+
+    use alloc::{borrow::ToOwned, string::String, vec::Vec};
+
+    use super::{Component, TwzPath};
+    use crate::{
+        fd::{NameResolver, NameRoot},
+        Result,
+    };
+
+    /// An owned, mutable Twizzler name.
+    #[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct TwzPathBuf {
+        inner: String,
+    }
+
+    impl TwzPathBuf {
+        /// Create an empty name.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Get the name of a root of the calling thread's name context.
+        pub fn from_nameroot(root: NameRoot) -> Result<Self> {
+            crate::fd::twz_rt_get_nameroot_owned(root).map(Self::from)
+        }
+
+        /// Borrow as a [TwzPath].
+        pub fn as_path(&self) -> &TwzPath {
+            TwzPath::new(&self.inner)
+        }
+
+        /// Extend this name with path. If path has a prefix, it replaces this name.
+        pub fn push(&mut self, path: impl AsRef<TwzPath>) {
+            let path = path.as_ref();
+            if path.has_prefix() {
+                self.inner.clear();
+            } else if !self.inner.is_empty() && !self.inner.ends_with('/') {
+                self.inner.push('/');
+            }
+            self.inner.push_str(path.as_str());
+        }
+
+        /// Remove the final component. Returns false if there is no parent.
+        pub fn pop(&mut self) -> bool {
+            match self.parent().map(|p| p.as_str().len()) {
+                Some(len) => {
+                    self.inner.truncate(len);
+                    true
+                }
+                None => false,
+            }
+        }
+
+        /// Replace the extension of the file name, or remove it if ext is empty. Returns false
+        /// if there is no file name.
+        pub fn set_extension(&mut self, ext: &str) -> bool {
+            let Some(stem) = self.file_stem() else {
+                return false;
+            };
+            let end = stem.as_ptr() as usize - self.inner.as_ptr() as usize + stem.len();
+            self.inner.truncate(end);
+            if !ext.is_empty() {
+                self.inner.push('.');
+                self.inner.push_str(ext);
+            }
+            true
+        }
+
+        /// Get the underlying string.
+        pub fn into_string(self) -> String {
+            self.inner
+        }
+    }
+
+    impl TwzPath {
+        /// Copy into an owned name.
+        pub fn to_path_buf(&self) -> TwzPathBuf {
+            TwzPathBuf::from(self.as_str())
+        }
+
+        /// Create an owned name by extending this name with path. See [TwzPathBuf::push].
+        pub fn join(&self, path: impl AsRef<TwzPath>) -> TwzPathBuf {
+            let mut buf = self.to_path_buf();
+            buf.push(path);
+            buf
+        }
+
+        /// Lexically remove `.` components and resolve `..` components against the preceding
+        /// component, without consulting the namespace. A `..` directly after a prefix is
+        /// dropped, and leading `..` components of names without a prefix are kept. Note that
+        /// this may not match resolution if a removed component is a symlink.
+        pub fn normalize(&self) -> TwzPathBuf {
+            let mut prefix = None;
+            let mut parts: Vec<&str> = Vec::new();
+            for comp in self.components() {
+                match comp {
+                    Component::Prefix(root) => prefix = Some(root),
+                    Component::CurDir => {}
+                    Component::ParentDir => match parts.last() {
+                        Some(&last) if last != ".." => {
+                            parts.pop();
+                        }
+                        _ if prefix.is_some() => {}
+                        _ => parts.push(".."),
+                    },
+                    Component::Normal(s) => parts.push(s),
+                }
+            }
+            let mut out = String::new();
+            match prefix {
+                Some(root) => {
+                    out.push_str(super::prefix_str(root));
+                    if root != NameRoot::Root && !parts.is_empty() {
+                        out.push('/');
+                    }
+                }
+                None if parts.is_empty() => out.push('.'),
+                // Keep a first component that looks like a prefix from becoming one.
+                None if super::split_prefix(parts[0]).is_some() => out.push_str("./"),
+                None => {}
+            }
+            out.push_str(&parts.join("/"));
+            TwzPathBuf::from(out)
+        }
+
+        /// Get the canonical form of this name from the runtime's default name resolver,
+        /// resolving symlinks and name roots.
+        pub fn canonicalize(&self) -> Result<TwzPathBuf> {
+            crate::fd::twz_rt_canon_name_owned(NameResolver::Default, self).map(TwzPathBuf::from)
+        }
+    }
+
+    impl ToOwned for TwzPath {
+        type Owned = TwzPathBuf;
+
+        fn to_owned(&self) -> TwzPathBuf {
+            self.to_path_buf()
+        }
+    }
+
+    impl core::borrow::Borrow<TwzPath> for TwzPathBuf {
+        fn borrow(&self) -> &TwzPath {
+            self.as_path()
+        }
+    }
+
+    impl core::ops::Deref for TwzPathBuf {
+        type Target = TwzPath;
+
+        fn deref(&self) -> &TwzPath {
+            self.as_path()
+        }
+    }
+
+    impl AsRef<TwzPath> for TwzPathBuf {
+        fn as_ref(&self) -> &TwzPath {
+            self.as_path()
+        }
+    }
+
+    impl AsRef<TwzPath> for String {
+        fn as_ref(&self) -> &TwzPath {
+            TwzPath::new(self)
+        }
+    }
+
+    impl AsRef<str> for TwzPathBuf {
+        fn as_ref(&self) -> &str {
+            &self.inner
+        }
+    }
+
+    impl From<String> for TwzPathBuf {
+        fn from(inner: String) -> Self {
+            Self { inner }
+        }
+    }
+
+    impl From<&str> for TwzPathBuf {
+        fn from(s: &str) -> Self {
+            Self { inner: s.into() }
+        }
+    }
+
+    impl From<&TwzPath> for TwzPathBuf {
+        fn from(path: &TwzPath) -> Self {
+            path.to_path_buf()
+        }
+    }
+
+    impl core::fmt::Display for TwzPathBuf {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.write_str(&self.inner)
+        }
+    }
+
+    impl core::fmt::Debug for TwzPathBuf {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "{:?}", self.inner)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn components(name: &str) -> impl Iterator<Item = Component<'_>> {
+        TwzPath::new(name).components()
+    }
+
+    #[test]
+    fn prefixes() {
+        assert!(components("/a//b/").eq([
+            Component::Prefix(NameRoot::Root),
+            Component::Normal("a"),
+            Component::Normal("b"),
+        ]));
+        assert!(components("~/a").eq([Component::Prefix(NameRoot::Home), Component::Normal("a")]));
+        assert!(components("~temp").eq([Component::Prefix(NameRoot::Temp)]));
+        assert!(
+            components("~exe//x").eq([Component::Prefix(NameRoot::Exe), Component::Normal("x")])
+        );
+        assert!(components("~tmp/x").eq([Component::Normal("~tmp"), Component::Normal("x")]));
+        assert!(components("./~").eq([Component::CurDir, Component::Normal("~")]));
+        assert!(components("a/../.").eq([
+            Component::Normal("a"),
+            Component::ParentDir,
+            Component::CurDir,
+        ]));
+
+        assert_eq!(TwzPath::new("/a").name_root(), NameRoot::Root);
+        assert_eq!(TwzPath::new("~").name_root(), NameRoot::Home);
+        assert_eq!(TwzPath::new("~temp/a").name_root(), NameRoot::Temp);
+        assert_eq!(TwzPath::new("~exe").name_root(), NameRoot::Exe);
+        assert_eq!(TwzPath::new("a/~").name_root(), NameRoot::Current);
+        assert_eq!(TwzPath::new("./~").name_root(), NameRoot::Current);
+        assert!(!TwzPath::new("").has_prefix());
+    }
+
+    #[test]
+    fn parent() {
+        let parent = |name| TwzPath::new(name).parent().map(TwzPath::as_str);
+        assert_eq!(parent("/a/b"), Some("/a"));
+        assert_eq!(parent("/a//b/"), Some("/a"));
+        assert_eq!(parent("/a"), Some("/"));
+        assert_eq!(parent("/"), None);
+        assert_eq!(parent("a"), Some(""));
+        assert_eq!(parent(""), None);
+        assert_eq!(parent("~temp/a"), Some("~temp"));
+        assert_eq!(parent("~temp/"), None);
+        assert_eq!(parent("~"), None);
+    }
+
+    #[test]
+    fn file_name() {
+        let path = TwzPath::new("a/b.tar.gz");
+        assert_eq!(path.file_name(), Some("b.tar.gz"));
+        assert_eq!(path.file_stem(), Some("b.tar"));
+        assert_eq!(path.extension(), Some("gz"));
+        let path = TwzPath::new("a/.profile");
+        assert_eq!(path.file_stem(), Some(".profile"));
+        assert_eq!(path.extension(), None);
+        assert_eq!(TwzPath::new("a/..").file_name(), None);
+        assert_eq!(TwzPath::new("~exe").file_name(), None);
+        assert!(TwzPath::new("/a/b/c").starts_with("/a/b"));
+        assert!(!TwzPath::new("/a/bc").starts_with("/a/b"));
+        assert!(!TwzPath::new("a/b").starts_with("/a"));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn join() {
+        let join = |a, b| TwzPath::new(a).join(b).into_string();
+        assert_eq!(join("a", "b"), "a/b");
+        assert_eq!(join("a/", "b"), "a/b");
+        assert_eq!(join("", "b"), "b");
+        assert_eq!(join("a", "/b"), "/b");
+        assert_eq!(join("a", "~exe/b"), "~exe/b");
+        assert_eq!(join("a", "./~"), "a/./~");
+
+        let mut buf = TwzPathBuf::from("/a/b.txt");
+        assert!(buf.set_extension("md"));
+        assert_eq!(buf.as_str(), "/a/b.md");
+        assert!(buf.pop());
+        assert_eq!(buf.as_str(), "/a");
+        assert!(buf.pop());
+        assert_eq!(buf.as_str(), "/");
+        assert!(!buf.pop());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn normalize() {
+        let normalize = |name| TwzPath::new(name).normalize().into_string();
+        assert_eq!(normalize("/a/./b/../c"), "/a/c");
+        assert_eq!(normalize("/.."), "/");
+        assert_eq!(normalize("a/b/../../.."), "..");
+        assert_eq!(normalize("../a/.."), "..");
+        assert_eq!(normalize("./a//b/"), "a/b");
+        assert_eq!(normalize(""), ".");
+        assert_eq!(normalize("a/.."), ".");
+        assert_eq!(normalize("~/a/.."), "~");
+        assert_eq!(normalize("~temp/../a"), "~temp/a");
+        assert_eq!(normalize("./~/a"), "./~/a");
+        assert_eq!(normalize("./~exe"), "./~exe");
+    }
+}
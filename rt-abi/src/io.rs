@@ -1,15 +1,30 @@
 //! Runtime interface for IO-like operations.
 
 #![allow(unused_variables)]
-use core::{mem::MaybeUninit, sync::atomic::AtomicU64};
+use core::{mem::MaybeUninit, sync::atomic::AtomicU64, time::Duration};
 
 use crate::{
     bindings::wait_kind,
-    error::RawTwzError,
+    error::{ArgumentError, GenericError, RawTwzError, TwzError},
     fd::{RawFd, SocketAddress},
     nk, Result,
 };
 
+#[cfg(feature = "alloc")]
+mod event;
+#[cfg(feature = "alloc")]
+pub use event::*;
+mod queue;
+pub use queue::*;
+mod traits;
+pub use traits::*;
+mod buffered;
+pub use buffered::*;
+pub mod config;
+pub use config::ConfigRegister;
+mod tty;
+pub use tty::*;
+
 bitflags::bitflags! {
     /// Possible flags for IO operations.
     #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -131,16 +146,38 @@ pub fn twz_rt_fd_pwrite(fd: RawFd, buf: &[u8], ctx: &mut IoCtx) -> Result<usize>
     }
 }
 
+impl SeekFrom {
+    fn whence_offset(&self) -> (crate::bindings::whence, i64) {
+        match *self {
+            SeekFrom::Start(s) => (crate::bindings::WHENCE_START, s as i64),
+            SeekFrom::End(s) => (crate::bindings::WHENCE_END, s),
+            SeekFrom::Current(s) => (crate::bindings::WHENCE_CURRENT, s),
+        }
+    }
+}
+
 /// Seek a file descriptor, changing the internal position.
 pub fn twz_rt_fd_seek(fd: RawFd, seek: SeekFrom) -> Result<usize> {
-    let (whence, off) = match seek {
-        SeekFrom::Start(s) => (crate::bindings::WHENCE_START, s as i64),
-        SeekFrom::End(s) => (crate::bindings::WHENCE_END, s),
-        SeekFrom::Current(s) => (crate::bindings::WHENCE_CURRENT, s),
-    };
+    let (whence, off) = seek.whence_offset();
     unsafe { nk!(crate::bindings::twz_rt_fd_seek(fd, whence, off).into()) }
 }
 
+/// Seek a file descriptor to the start of the next allocated range at or after off. Fails with
+/// [IoError::SeekFailed](crate::error::IoError::SeekFailed) if there is no data at or after off.
+pub fn twz_rt_fd_seek_data(fd: RawFd, off: u64) -> Result<usize> {
+    unsafe {
+        nk!(crate::bindings::twz_rt_fd_seek(fd, crate::bindings::WHENCE_DATA, off as i64).into())
+    }
+}
+
+/// Seek a file descriptor to the start of the next hole at or after off, or to the end of the
+/// file.
+pub fn twz_rt_fd_seek_hole(fd: RawFd, off: u64) -> Result<usize> {
+    unsafe {
+        nk!(crate::bindings::twz_rt_fd_seek(fd, crate::bindings::WHENCE_HOLE, off as i64).into())
+    }
+}
+
 #[derive(Copy, Clone)]
 #[repr(transparent)]
 pub struct Endpoint(crate::bindings::endpoint);
@@ -239,7 +276,7 @@ pub type IoSlice = crate::bindings::iovec;
 /// function returns an error.
 pub fn twz_rt_fd_preadv(fd: RawFd, ios: &[IoSlice], ctx: &mut IoCtx) -> Result<usize> {
     unsafe {
-        nk!(crate::bindings::twz_rt_fd_pwritev(fd, ios.as_ptr(), ios.len(), &mut ctx.0).into())
+        nk!(crate::bindings::twz_rt_fd_preadv(fd, ios.as_ptr(), ios.len(), &mut ctx.0).into())
     }
 }
 
@@ -253,6 +290,22 @@ pub fn twz_rt_fd_pwritev(fd: RawFd, ios: &[IoSlice], ctx: &mut IoCtx) -> Result<
     }
 }
 
+/// Copy up to len bytes from src at src_off to dst at dst_off, without reading the data into a
+/// buffer of our own. Object-backed descriptors are copied object to object. On success,
+/// returns the number of bytes actually copied, which may be fewer than requested, and is 0 at
+/// the end of src. Neither descriptor's position is used or updated.
+pub fn twz_rt_fd_copy_range(
+    src: RawFd,
+    src_off: u64,
+    dst: RawFd,
+    dst_off: u64,
+    len: usize,
+) -> Result<usize> {
+    unsafe { nk!(crate::bindings::twz_rt_fd_copy_range(src, src_off, dst, dst_off, len).into()) }
+}
+
+/// Get the value of config register reg. The type of the value must match the register; prefer
+/// the typed registers in [config].
 pub fn twz_rt_fd_get_config<T>(fd: RawFd, reg: u32) -> Result<T> {
     let mut val = core::mem::MaybeUninit::<T>::uninit();
     let e = unsafe {
@@ -270,6 +323,8 @@ pub fn twz_rt_fd_get_config<T>(fd: RawFd, reg: u32) -> Result<T> {
     Ok(unsafe { val.assume_init() })
 }
 
+/// Set the value of config register reg. The type of the value must match the register; prefer
+/// the typed registers in [config].
 pub fn twz_rt_fd_set_config<T>(fd: RawFd, reg: u32, val: T) -> Result<()> {
     let e = unsafe {
         nk!(crate::bindings::twz_rt_fd_set_config(
@@ -309,3 +364,284 @@ pub fn twz_rt_fd_waitpoint(fd: RawFd, kind: wait_kind) -> Result<(*const AtomicU
         unsafe { ready.assume_init() },
     ))
 }
+
+/// Events a [Poller] waits for on a file descriptor.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Interest {
+    /// Wait for the descriptor to be readable.
+    pub read: bool,
+    /// Wait for the descriptor to be writable.
+    pub write: bool,
+    /// Wait for exceptional conditions, such as out-of-band data.
+    pub except: bool,
+}
+
+impl Interest {
+    pub const READ: Self = Self {
+        read: true,
+        write: false,
+        except: false,
+    };
+    pub const WRITE: Self = Self {
+        read: false,
+        write: true,
+        except: false,
+    };
+    pub const READ_WRITE: Self = Self {
+        read: true,
+        write: true,
+        except: false,
+    };
+
+    fn events(&self) -> u32 {
+        let mut events = 0;
+        if self.read {
+            events |= crate::bindings::POLLIN;
+        }
+        if self.write {
+            events |= crate::bindings::POLLOUT;
+        }
+        if self.except {
+            events |= crate::bindings::POLLPRI;
+        }
+        events
+    }
+}
+
+/// Readiness of a file descriptor, as reported by [Poller::poll].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Readiness {
+    /// The file descriptor.
+    pub fd: RawFd,
+    /// The descriptor is readable.
+    pub read: bool,
+    /// The descriptor is writable.
+    pub write: bool,
+    /// An exceptional condition is pending on the descriptor.
+    pub except: bool,
+    /// The descriptor has an error pending. Reported regardless of interest.
+    pub error: bool,
+    /// The other end of the descriptor hung up. Reported regardless of interest.
+    pub hangup: bool,
+    /// The descriptor is not open. Reported regardless of interest.
+    pub invalid: bool,
+}
+
+/// An entry in the set of descriptors polled by a [Poller].
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(transparent)]
+pub struct PollEntry(crate::bindings::pollfd);
+
+impl PollEntry {
+    /// Make a new entry, waiting for the events in interest on fd.
+    pub fn new(fd: RawFd, interest: Interest) -> Self {
+        Self(crate::bindings::pollfd {
+            fd,
+            events: interest.events() as _,
+            revents: 0,
+        })
+    }
+
+    /// Get the file descriptor.
+    pub fn fd(&self) -> RawFd {
+        self.0.fd
+    }
+
+    /// Get the readiness reported by the last poll, or None if nothing was reported.
+    pub fn readiness(&self) -> Option<Readiness> {
+        let revents = self.0.revents as u32;
+        if revents == 0 {
+            return None;
+        }
+        Some(Readiness {
+            fd: self.0.fd,
+            read: revents & crate::bindings::POLLIN != 0,
+            write: revents & crate::bindings::POLLOUT != 0,
+            except: revents & crate::bindings::POLLPRI != 0,
+            error: revents & crate::bindings::POLLERR != 0,
+            hangup: revents & crate::bindings::POLLHUP != 0,
+            invalid: revents & crate::bindings::POLLNVAL != 0,
+        })
+    }
+}
+
+impl From<(RawFd, Interest)> for PollEntry {
+    fn from((fd, interest): (RawFd, Interest)) -> Self {
+        Self::new(fd, interest)
+    }
+}
+
+/// Waits for readiness on a set of file descriptors, using [twz_rt_fd_poll].
+pub struct Poller<'a> {
+    entries: &'a mut [PollEntry],
+}
+
+impl<'a> Poller<'a> {
+    /// Make a new poller over the descriptors in entries.
+    pub fn new(entries: &'a mut [PollEntry]) -> Self {
+        Self { entries }
+    }
+
+    /// Get the entries being polled.
+    pub fn entries(&self) -> &[PollEntry] {
+        self.entries
+    }
+
+    /// Wait until at least one descriptor is ready, or the timeout expires. A timeout of None
+    /// waits indefinitely. Returns an iterator over the ready descriptors. If the timeout expires
+    /// with nothing ready, returns [GenericError::WouldBlock] for a zero timeout, and
+    /// [GenericError::TimedOut] otherwise.
+    pub fn poll(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<impl Iterator<Item = Readiness> + '_> {
+        for entry in self.entries.iter_mut() {
+            entry.0.revents = 0;
+        }
+        match twz_rt_fd_poll(self.entries, timeout) {
+            Ok(0) | Err(TwzError::Generic(GenericError::TimedOut | GenericError::WouldBlock)) => {
+                Err(timeout_error(timeout))
+            }
+            Ok(_) => Ok(self.entries.iter().filter_map(|e| e.readiness())),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn timeout_error(timeout: Option<Duration>) -> TwzError {
+    if timeout == Some(Duration::ZERO) {
+        GenericError::WouldBlock.into()
+    } else {
+        GenericError::TimedOut.into()
+    }
+}
+
+/// Poll a set of file descriptors, returning the number of entries with readiness reported.
+/// Returns 0 if the timeout expires first.
+pub fn twz_rt_fd_poll(entries: &mut [PollEntry], timeout: Option<Duration>) -> Result<usize> {
+    unsafe {
+        nk!(crate::bindings::twz_rt_fd_poll(
+            entries.as_mut_ptr().cast(),
+            entries.len(),
+            timeout.into()
+        )
+        .into())
+    }
+}
+
+/// A set of file descriptors for [twz_rt_fd_select].
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+pub struct FdSet(crate::bindings::fd_set);
+
+const FD_SET_WORD_BITS: usize = usize::BITS as usize;
+
+// The set is accessed as an array of words, with descriptor n at bit n % FD_SET_WORD_BITS of word
+// n / FD_SET_WORD_BITS, matching FD_SET.
+const _: () = assert!(size_of::<crate::bindings::fd_set>().is_multiple_of(size_of::<usize>()));
+const _: () = assert!(align_of::<crate::bindings::fd_set>() >= align_of::<usize>());
+
+impl FdSet {
+    /// The number of descriptors a set can hold. Descriptors from 0 up to, but not including,
+    /// this number can be inserted.
+    pub const CAPACITY: usize = size_of::<crate::bindings::fd_set>() * 8;
+
+    /// Make a new, empty set.
+    pub fn new() -> Self {
+        // Safety: fd_set is plain data, and all zeros is the empty set.
+        Self(unsafe { MaybeUninit::zeroed().assume_init() })
+    }
+
+    fn words(&self) -> &[usize] {
+        // Safety: fd_set is plain data, sized and aligned for these words (checked above).
+        unsafe {
+            core::slice::from_raw_parts(
+                (&self.0 as *const crate::bindings::fd_set).cast(),
+                Self::CAPACITY / FD_SET_WORD_BITS,
+            )
+        }
+    }
+
+    fn words_mut(&mut self) -> &mut [usize] {
+        // Safety: as for words.
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                (&mut self.0 as *mut crate::bindings::fd_set).cast(),
+                Self::CAPACITY / FD_SET_WORD_BITS,
+            )
+        }
+    }
+
+    fn index(fd: RawFd) -> Option<(usize, usize)> {
+        let fd = usize::try_from(fd).ok().filter(|fd| *fd < Self::CAPACITY)?;
+        Some((fd / FD_SET_WORD_BITS, 1 << (fd % FD_SET_WORD_BITS)))
+    }
+
+    /// Add fd to the set. Fails with [ArgumentError::InvalidArgument] if fd is negative or not
+    /// below [FdSet::CAPACITY].
+    pub fn insert(&mut self, fd: RawFd) -> Result<()> {
+        let (word, bit) = Self::index(fd).ok_or(ArgumentError::InvalidArgument)?;
+        self.words_mut()[word] |= bit;
+        Ok(())
+    }
+
+    /// Remove fd from the set, if it is present.
+    pub fn remove(&mut self, fd: RawFd) {
+        if let Some((word, bit)) = Self::index(fd) {
+            self.words_mut()[word] &= !bit;
+        }
+    }
+
+    /// Check if fd is in the set.
+    pub fn contains(&self, fd: RawFd) -> bool {
+        Self::index(fd).is_some_and(|(word, bit)| self.words()[word] & bit != 0)
+    }
+
+    /// Remove all descriptors from the set.
+    pub fn clear(&mut self) {
+        self.words_mut().fill(0);
+    }
+}
+
+impl Default for FdSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Debug for FdSet {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_set()
+            .entries((0..Self::CAPACITY as RawFd).filter(|fd| self.contains(*fd)))
+            .finish()
+    }
+}
+
+/// Select over sets of file descriptors numbered below nfds, returning the number of ready
+/// descriptors. On return, each set holds only its ready descriptors. Returns 0 if the timeout
+/// expires first. Sets that are None are not checked. Fails with [ArgumentError::InvalidArgument]
+/// if nfds is larger than [FdSet::CAPACITY].
+pub fn twz_rt_fd_select(
+    nfds: usize,
+    read: Option<&mut FdSet>,
+    write: Option<&mut FdSet>,
+    except: Option<&mut FdSet>,
+    timeout: Option<Duration>,
+) -> Result<usize> {
+    fn ptr(set: Option<&mut FdSet>) -> *mut crate::bindings::fd_set {
+        set.map_or(core::ptr::null_mut(), |s| &mut s.0)
+    }
+    if nfds > FdSet::CAPACITY {
+        return Err(ArgumentError::InvalidArgument.into());
+    }
+    unsafe {
+        nk!(crate::bindings::twz_rt_fd_select(
+            nfds,
+            ptr(read),
+            ptr(write),
+            ptr(except),
+            timeout.into()
+        )
+        .into())
+    }
+}
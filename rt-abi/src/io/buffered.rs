@@ -0,0 +1,482 @@
+//! Buffered readers and writers over file descriptors, to batch small reads and writes into fewer
+//! runtime calls.
+//!
+//! Each type either uses the descriptor's internal position (the default), or, once given an
+//! offset with `at_offset`, tracks its own position and passes it in the [IoCtx] of every call,
+//! leaving the descriptor's position alone. The second mode suits descriptors shared with other
+//! readers or writers.
+
+use core::{alloc::Layout, marker::PhantomData, ptr::NonNull};
+
+use super::{
+    traits::is_interrupted, twz_rt_fd_pread, twz_rt_fd_pwrite, twz_rt_fd_seek, BufRead, IoCtx,
+    Read, Seek, SeekFrom, Write,
+};
+use crate::{
+    alloc::{twz_rt_dealloc, twz_rt_malloc, AllocFlags},
+    error::{ArgumentError, IoError, ResourceError, TwzError},
+    fd::{twz_rt_fd_get_info, RawFd},
+    Result,
+};
+
+/// The buffer length used by the `new` constructors.
+pub const DEFAULT_BUFFER_LEN: usize = 4096;
+
+/// Memory for a buffered reader or writer, either borrowed from the caller or allocated from the
+/// runtime and freed on drop.
+struct Buffer<'a> {
+    ptr: NonNull<u8>,
+    len: usize,
+    owned: bool,
+    _pd: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> Buffer<'a> {
+    fn borrowed(buf: &'a mut [u8]) -> Result<Self> {
+        if buf.is_empty() {
+            return Err(ArgumentError::InvalidArgument.into());
+        }
+        Ok(Self {
+            len: buf.len(),
+            ptr: NonNull::from(buf).cast(),
+            owned: false,
+            _pd: PhantomData,
+        })
+    }
+
+    fn allocate(len: usize) -> Result<Self> {
+        if len == 0 {
+            return Err(ArgumentError::InvalidArgument.into());
+        }
+        let layout = Layout::array::<u8>(len).map_err(|_| ArgumentError::InvalidArgument)?;
+        let ptr = twz_rt_malloc(layout, AllocFlags::ZERO_MEMORY)
+            .and_then(NonNull::new)
+            .ok_or(ResourceError::OutOfMemory)?;
+        Ok(Self {
+            ptr,
+            len,
+            owned: true,
+            _pd: PhantomData,
+        })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        // Safety: ptr is valid and initialized for len bytes for 'a, and we hold the only
+        // reference.
+        unsafe { core::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        // Safety: see as_slice.
+        unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for Buffer<'_> {
+    fn drop(&mut self) {
+        if self.owned {
+            // Safety: allocated in Buffer::allocate with this layout, and no references remain.
+            unsafe {
+                twz_rt_dealloc(
+                    self.ptr.as_ptr(),
+                    Layout::array::<u8>(self.len).unwrap(),
+                    AllocFlags::empty(),
+                )
+            };
+        }
+    }
+}
+
+/// A descriptor, the context for I/O on it, and the position if not using the descriptor's own.
+struct Cursor {
+    fd: RawFd,
+    ctx: IoCtx,
+    pos: Option<u64>,
+}
+
+impl Cursor {
+    fn new(fd: RawFd) -> Self {
+        Self {
+            fd,
+            ctx: IoCtx::default(),
+            pos: None,
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = twz_rt_fd_pread(self.fd, buf, &mut self.ctx.offset(self.pos))?;
+        self.advance(n);
+        Ok(n)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = twz_rt_fd_pwrite(self.fd, buf, &mut self.ctx.offset(self.pos))?;
+        self.advance(n);
+        Ok(n)
+    }
+
+    fn advance(&mut self, n: usize) {
+        if let Some(pos) = &mut self.pos {
+            *pos += n as u64;
+        }
+    }
+
+    /// Seek, where base is the logical position, which differs from the cursor's by the amount
+    /// of data buffered.
+    fn seek(&mut self, seek: SeekFrom, base: u64) -> Result<u64> {
+        if self.pos.is_none() {
+            let seek = match seek {
+                SeekFrom::Current(off) => {
+                    SeekFrom::Start(base.checked_add_signed(off).ok_or(IoError::SeekFailed)?)
+                }
+                seek => seek,
+            };
+            return twz_rt_fd_seek(self.fd, seek).map(|p| p as u64);
+        }
+        let new = match seek {
+            SeekFrom::Start(off) => Some(off),
+            SeekFrom::Current(off) => base.checked_add_signed(off),
+            SeekFrom::End(off) => twz_rt_fd_get_info(self.fd)?.size.checked_add_signed(off),
+        };
+        let new = new.ok_or(IoError::SeekFailed)?;
+        self.pos = Some(new);
+        Ok(new)
+    }
+
+    /// The logical position of the cursor, given how much data is buffered ahead of it (for
+    /// reads, negative) or behind it (for writes, positive).
+    fn position(&self, buffered: i64) -> Result<u64> {
+        match self.pos {
+            Some(pos) => Ok(pos.wrapping_add_signed(buffered)),
+            None => {
+                let pos = twz_rt_fd_seek(self.fd, SeekFrom::Current(0))? as u64;
+                Ok(pos.wrapping_add_signed(buffered))
+            }
+        }
+    }
+}
+
+/// Adds buffering to reads from a file descriptor. The descriptor is not closed on drop.
+///
+/// In the descriptor's position mode, the descriptor's position runs ahead of the reader by the
+/// amount buffered, so reading from the descriptor directly while a reader is in use will skip
+/// data.
+pub struct BufReader<'a> {
+    cursor: Cursor,
+    buf: Buffer<'a>,
+    start: usize,
+    end: usize,
+}
+
+impl<'a> BufReader<'a> {
+    /// Make a reader for fd, with a [DEFAULT_BUFFER_LEN] buffer from the runtime.
+    pub fn new(fd: RawFd) -> Result<Self> {
+        Self::with_capacity(fd, DEFAULT_BUFFER_LEN)
+    }
+
+    /// Make a reader for fd, with a buffer of capacity bytes from the runtime.
+    pub fn with_capacity(fd: RawFd, capacity: usize) -> Result<Self> {
+        Ok(Self::from_buffer(fd, Buffer::allocate(capacity)?))
+    }
+
+    /// Make a reader for fd, using buf as its buffer.
+    pub fn with_buffer(fd: RawFd, buf: &'a mut [u8]) -> Result<Self> {
+        Ok(Self::from_buffer(fd, Buffer::borrowed(buf)?))
+    }
+
+    fn from_buffer(fd: RawFd, buf: Buffer<'a>) -> Self {
+        Self {
+            cursor: Cursor::new(fd),
+            buf,
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// Read from offset, tracking the position in the reader rather than the descriptor.
+    pub fn at_offset(mut self, offset: u64) -> Self {
+        self.discard_buffer();
+        self.cursor.pos = Some(offset);
+        self
+    }
+
+    /// Use ctx for reads, for its flags and timeout. The offset in ctx is ignored.
+    pub fn ctx(mut self, ctx: IoCtx) -> Self {
+        self.cursor.ctx = ctx;
+        self
+    }
+
+    /// The file descriptor.
+    pub fn fd(&self) -> RawFd {
+        self.cursor.fd
+    }
+
+    /// The buffered data, not yet returned by a read.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf.as_slice()[self.start..self.end]
+    }
+
+    /// The size of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.len
+    }
+
+    /// Drop the buffered data. In the reader's position mode, the next read starts where the
+    /// buffered data ended.
+    pub fn discard_buffer(&mut self) {
+        self.start = 0;
+        self.end = 0;
+    }
+
+    /// The position of the next byte to be returned by a read.
+    pub fn position(&self) -> Result<u64> {
+        self.cursor.position(-((self.end - self.start) as i64))
+    }
+
+    /// Get the file descriptor back. Buffered data is lost.
+    pub fn into_inner(self) -> RawFd {
+        self.cursor.fd
+    }
+}
+
+impl Read for BufReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        // Skip the buffer for reads at least as large as it.
+        if self.start == self.end && buf.len() >= self.capacity() {
+            return self.cursor.read(buf);
+        }
+        let avail = self.fill_buf()?;
+        let n = avail.len().min(buf.len());
+        buf[..n].copy_from_slice(&avail[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for BufReader<'_> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        if self.start == self.end {
+            let n = self.cursor.read(self.buf.as_mut_slice())?;
+            self.start = 0;
+            self.end = n;
+        }
+        Ok(self.buffer())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.start = (self.start + amt).min(self.end);
+    }
+}
+
+impl Seek for BufReader<'_> {
+    /// Seek, discarding the buffered data.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let base = self.position()?;
+        let new = self.cursor.seek(pos, base)?;
+        self.discard_buffer();
+        Ok(new)
+    }
+}
+
+/// Adds buffering to writes to a file descriptor. Buffered data is written out when the buffer
+/// fills, on [Write::flush], and on drop, where errors are ignored; flush first to see them. The
+/// descriptor is not closed on drop.
+pub struct BufWriter<'a> {
+    cursor: Cursor,
+    buf: Buffer<'a>,
+    len: usize,
+}
+
+impl<'a> BufWriter<'a> {
+    /// Make a writer for fd, with a [DEFAULT_BUFFER_LEN] buffer from the runtime.
+    pub fn new(fd: RawFd) -> Result<Self> {
+        Self::with_capacity(fd, DEFAULT_BUFFER_LEN)
+    }
+
+    /// Make a writer for fd, with a buffer of capacity bytes from the runtime.
+    pub fn with_capacity(fd: RawFd, capacity: usize) -> Result<Self> {
+        Ok(Self::from_buffer(fd, Buffer::allocate(capacity)?))
+    }
+
+    /// Make a writer for fd, using buf as its buffer.
+    pub fn with_buffer(fd: RawFd, buf: &'a mut [u8]) -> Result<Self> {
+        Ok(Self::from_buffer(fd, Buffer::borrowed(buf)?))
+    }
+
+    fn from_buffer(fd: RawFd, buf: Buffer<'a>) -> Self {
+        Self {
+            cursor: Cursor::new(fd),
+            buf,
+            len: 0,
+        }
+    }
+
+    /// Write starting at offset, tracking the position in the writer rather than the descriptor.
+    /// Any buffered data is first written at the old position.
+    pub fn at_offset(mut self, offset: u64) -> Result<Self> {
+        self.flush_buf()?;
+        self.cursor.pos = Some(offset);
+        Ok(self)
+    }
+
+    /// Use ctx for writes, for its flags and timeout. The offset in ctx is ignored.
+    pub fn ctx(mut self, ctx: IoCtx) -> Self {
+        self.cursor.ctx = ctx;
+        self
+    }
+
+    /// The file descriptor.
+    pub fn fd(&self) -> RawFd {
+        self.cursor.fd
+    }
+
+    /// The buffered data, not yet written to the descriptor.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf.as_slice()[..self.len]
+    }
+
+    /// The size of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.len
+    }
+
+    /// The position the next write will go to.
+    pub fn position(&self) -> Result<u64> {
+        self.cursor.position(self.len as i64)
+    }
+
+    /// Write out the buffered data. On error, the data not yet written stays buffered.
+    fn flush_buf(&mut self) -> Result<()> {
+        let mut written = 0;
+        let res = loop {
+            if written == self.len {
+                break Ok(());
+            }
+            match self.cursor.write(&self.buf.as_slice()[written..self.len]) {
+                Ok(0) => break Err(IoError::DataLoss.into()),
+                Ok(n) => written += n,
+                Err(e) if is_interrupted(&e) => {}
+                Err(e) => break Err(e),
+            }
+        };
+        self.buf.as_mut_slice().copy_within(written..self.len, 0);
+        self.len -= written;
+        res
+    }
+
+    /// Copy as much of buf as fits into the buffer, returning the amount copied.
+    fn write_to_buf(&mut self, buf: &[u8]) -> usize {
+        let n = buf.len().min(self.capacity() - self.len);
+        self.buf.as_mut_slice()[self.len..(self.len + n)].copy_from_slice(&buf[..n]);
+        self.len += n;
+        n
+    }
+
+    /// Flush and get the file descriptor back. If flushing fails, the error is returned along
+    /// with the writer, with the unwritten data still buffered.
+    pub fn into_inner(mut self) -> core::result::Result<RawFd, (Self, TwzError)> {
+        match self.flush_buf() {
+            // Nothing is left buffered, so dropping self writes nothing.
+            Ok(()) => Ok(self.cursor.fd),
+            Err(e) => Err((self, e)),
+        }
+    }
+}
+
+impl Write for BufWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.len + buf.len() > self.capacity() {
+            self.flush_buf()?;
+        }
+        // Skip the buffer for writes at least as large as it.
+        if buf.len() >= self.capacity() {
+            return self.cursor.write(buf);
+        }
+        Ok(self.write_to_buf(buf))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.flush_buf()
+    }
+}
+
+impl Seek for BufWriter<'_> {
+    /// Write out the buffered data, then seek.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.flush_buf()?;
+        let base = self.position()?;
+        self.cursor.seek(pos, base)
+    }
+}
+
+impl Drop for BufWriter<'_> {
+    fn drop(&mut self) {
+        let _ = self.flush_buf();
+    }
+}
+
+/// A [BufWriter] that also writes out buffered data at the end of each line, for output read by
+/// people, such as to a terminal.
+pub struct LineWriter<'a> {
+    inner: BufWriter<'a>,
+}
+
+impl<'a> LineWriter<'a> {
+    /// Make a line writer for fd, with a [DEFAULT_BUFFER_LEN] buffer from the runtime.
+    pub fn new(fd: RawFd) -> Result<Self> {
+        BufWriter::new(fd).map(Self::from)
+    }
+
+    /// Make a line writer for fd, using buf as its buffer.
+    pub fn with_buffer(fd: RawFd, buf: &'a mut [u8]) -> Result<Self> {
+        BufWriter::with_buffer(fd, buf).map(Self::from)
+    }
+
+    /// Get the underlying writer.
+    pub fn get_ref(&self) -> &BufWriter<'a> {
+        &self.inner
+    }
+
+    /// Flush and get the file descriptor back. See [BufWriter::into_inner].
+    pub fn into_inner(self) -> core::result::Result<RawFd, (Self, TwzError)> {
+        self.inner
+            .into_inner()
+            .map_err(|(inner, e)| (Self { inner }, e))
+    }
+}
+
+impl<'a> From<BufWriter<'a>> for LineWriter<'a> {
+    fn from(inner: BufWriter<'a>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Write for LineWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let Some(idx) = buf.iter().rposition(|&b| b == b'\n') else {
+            // Finish off a completed line left buffered by an earlier short write.
+            if self.inner.buffer().last() == Some(&b'\n') {
+                self.inner.flush_buf()?;
+            }
+            return self.inner.write(buf);
+        };
+        let (lines, tail) = buf.split_at(idx + 1);
+        self.inner.flush_buf()?;
+        let n = if lines.len() >= self.inner.capacity() {
+            self.inner.cursor.write(lines)?
+        } else {
+            let n = self.inner.write_to_buf(lines);
+            // Keep the lines buffered if this fails; they were accepted by this call.
+            let _ = self.inner.flush_buf();
+            n
+        };
+        if n < lines.len() {
+            return Ok(n);
+        }
+        Ok(n + self.inner.write_to_buf(tail))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,194 @@
+//! Typed config registers, for getting and setting descriptor configuration without passing a
+//! register number and value type that don't match.
+//!
+//! Each register is a unit type implementing [ConfigRegister]:
+//! ```ignore
+//! config::Ttl::set(fd, 64)?;
+//! let timeout: Option<Duration> = config::ReadTimeout::get(fd)?;
+//! ```
+
+use core::time::Duration;
+
+use super::{twz_rt_fd_get_config, twz_rt_fd_set_config, IoFlags, Termios, Winsize};
+pub use crate::bindings::multicast_membership as MulticastMembership;
+use crate::{
+    bindings::option_duration,
+    fd::{RawFd, SocketAddress},
+    object::ObjID,
+    Result,
+};
+
+/// A descriptor config register, and the type of its value.
+pub trait ConfigRegister {
+    /// The register number, one of the IO_REGISTER_* values.
+    const REG: u32;
+    /// The register's value.
+    type Value;
+    /// The register's value laid out as the runtime expects, converted to and from
+    /// [ConfigRegister::Value].
+    type Raw: Copy + From<Self::Value> + Into<Self::Value>;
+
+    /// Read the register on fd.
+    fn get(fd: RawFd) -> Result<Self::Value> {
+        twz_rt_fd_get_config::<Self::Raw>(fd, Self::REG).map(Into::into)
+    }
+
+    /// Write the register on fd. Setting a register may have side effects.
+    fn set(fd: RawFd, val: Self::Value) -> Result<()> {
+        twz_rt_fd_set_config(fd, Self::REG, Self::Raw::from(val))
+    }
+}
+
+/// Define register types. The value of each register is given as one of:
+/// - `Val as CTy`, where Val is a `#[repr(transparent)]` wrapper around CTy, the C type the ABI
+///   specifies for the register, and is passed to the runtime as is. Its size and alignment are
+///   checked at compile time.
+/// - `Val => CTy`, where Val is converted to and from CTy.
+/// - `CTy` alone, for a value that is the C type itself.
+macro_rules! config_registers {
+    () => {};
+    ($(#[$attr:meta])* $name:ident = $reg:ident: $val:ty as $cty:ty; $($rest:tt)*) => {
+        config_registers!(@define $(#[$attr])* $name = $reg: $val, $val);
+        const _: () = assert!(
+            core::mem::size_of::<$val>() == core::mem::size_of::<$cty>()
+                && core::mem::align_of::<$val>() == core::mem::align_of::<$cty>()
+        );
+        config_registers!($($rest)*);
+    };
+    ($(#[$attr:meta])* $name:ident = $reg:ident: $val:ty => $cty:ty; $($rest:tt)*) => {
+        config_registers!(@define $(#[$attr])* $name = $reg: $val, $cty);
+        config_registers!($($rest)*);
+    };
+    ($(#[$attr:meta])* $name:ident = $reg:ident: $cty:ty; $($rest:tt)*) => {
+        config_registers!(@define $(#[$attr])* $name = $reg: $cty, $cty);
+        config_registers!($($rest)*);
+    };
+    (@define $(#[$attr:meta])* $name:ident = $reg:ident: $val:ty, $raw:ty) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy)]
+        pub struct $name;
+
+        impl ConfigRegister for $name {
+            const REG: u32 = crate::bindings::$reg;
+            type Value = $val;
+            type Raw = $raw;
+        }
+    };
+}
+
+config_registers! {
+    /// Local address of a socket.
+    Addr = IO_REGISTER_ADDR: SocketAddress as crate::bindings::socket_address;
+    /// Remote address of a connected socket.
+    Peer = IO_REGISTER_PEER: SocketAddress as crate::bindings::socket_address;
+    /// Socket options.
+    SocketOptions = IO_REGISTER_SOCKET_FLAGS: SocketFlags => u32;
+    /// How long closing a socket waits to send remaining data, or None to not wait.
+    Linger = IO_REGISTER_LINGER: Option<Duration> => option_duration;
+    /// Time-to-live of packets sent by a socket.
+    Ttl = IO_REGISTER_TTL: u32;
+    /// Default timeout for reads, or None to wait indefinitely.
+    ReadTimeout = IO_REGISTER_READTIMEOUT: Option<Duration> => option_duration;
+    /// Default timeout for writes, or None to wait indefinitely.
+    WriteTimeout = IO_REGISTER_WRITETIMEOUT: Option<Duration> => option_duration;
+    /// Status of the descriptor.
+    Status = IO_REGISTER_STATUS: StatusFlags => u64;
+    /// Signals pending on the descriptor. Setting the register clears the signals in the value.
+    Signal = IO_REGISTER_SIGNAL: SignalFlags => u64;
+    /// Terminal settings of a pty.
+    TerminalSettings = IO_REGISTER_TERMIOS: Termios;
+    /// Join or leave an IPv4 multicast group. Write-only.
+    MulticastV4 = IO_REGISTER_MULTICAST_V4: MulticastMembership;
+    /// Join or leave an IPv6 multicast group. Write-only.
+    MulticastV6 = IO_REGISTER_MULTICAST_V6: MulticastMembership;
+    /// Time-to-live of IPv4 multicast packets sent by a socket.
+    MulticastTtlV4 = IO_REGISTER_MULTICAST_TTL_V4: u32;
+    /// Default flags for I/O operations, combined with the flags of each operation's
+    /// [IoCtx](super::IoCtx).
+    DefaultIoFlags = IO_REGISTER_IO_FLAGS: IoFlags => crate::bindings::io_flags;
+    /// Window size of a pty. Setting it raises [SignalFlags::WINSIZE] on the pty's descriptors.
+    WindowSize = IO_REGISTER_WINSIZE: Winsize;
+    /// Foreground compartment of a pty, or a zero ID for none. The foreground compartment's
+    /// client descriptors receive the signals generated by input.
+    Foreground = IO_REGISTER_FOREGROUND: ObjID as crate::bindings::objid;
+}
+
+bitflags::bitflags! {
+    /// Socket options, the value of [SocketOptions].
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+    pub struct SocketFlags : u32 {
+        /// Send data as soon as possible, without coalescing small writes.
+        const NODELAY = crate::bindings::SOCKET_FLAGS_NODELAY;
+        /// Restrict an IPv6 socket to IPv6 traffic only.
+        const ONLYV6 = crate::bindings::SOCKET_FLAGS_ONLYV6;
+        /// Allow sending to broadcast addresses.
+        const BROADCAST = crate::bindings::SOCKET_FLAGS_BROADCAST;
+        /// Loop IPv4 multicast packets back to the local host.
+        const MULTICAST_LOOP_V4 = crate::bindings::SOCKET_FLAGS_MULTICAST_LOOP_V4;
+        /// Loop IPv6 multicast packets back to the local host.
+        const MULTICAST_LOOP_V6 = crate::bindings::SOCKET_FLAGS_MULTICAST_LOOP_V6;
+    }
+}
+
+bitflags::bitflags! {
+    /// Descriptor status, the value of [Status].
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+    pub struct StatusFlags : u64 {
+        /// The other end of the descriptor has gone away.
+        const TERMINATED = crate::bindings::STATUS_FLAG_TERMINATED;
+        /// The descriptor is ready for use.
+        const READY = crate::bindings::STATUS_FLAG_READY;
+    }
+}
+
+bitflags::bitflags! {
+    /// Signals pending on a descriptor, the value of [Signal].
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+    pub struct SignalFlags : u64 {
+        /// The window size of a pty changed.
+        const WINSIZE = crate::bindings::SIGNAL_FLAG_WINSIZE;
+        /// The INTR character was input, with [LocalFlags::ISIG](super::LocalFlags::ISIG) set.
+        const INTERRUPT = crate::bindings::SIGNAL_FLAG_INTERRUPT;
+        /// The QUIT character was input, with [LocalFlags::ISIG](super::LocalFlags::ISIG) set.
+        const QUIT = crate::bindings::SIGNAL_FLAG_QUIT;
+        /// The SUSP character was input, with [LocalFlags::ISIG](super::LocalFlags::ISIG) set.
+        const SUSPEND = crate::bindings::SIGNAL_FLAG_SUSPEND;
+    }
+}
+
+/// Convert flags to and from their raw register value, keeping unknown bits.
+macro_rules! flags_conversions {
+    ($($flags:ty => $raw:ty;)*) => {
+        $(
+            impl From<$flags> for $raw {
+                fn from(value: $flags) -> Self {
+                    value.bits()
+                }
+            }
+
+            impl From<$raw> for $flags {
+                fn from(value: $raw) -> Self {
+                    Self::from_bits_retain(value)
+                }
+            }
+        )*
+    };
+}
+
+flags_conversions! {
+    SocketFlags => u32;
+    StatusFlags => u64;
+    SignalFlags => u64;
+    IoFlags => crate::bindings::io_flags;
+}
+
+impl MulticastMembership {
+    /// Make the value of [MulticastV4] or [MulticastV6], to join or leave group on interface.
+    pub fn new(group: SocketAddress, interface: SocketAddress, join: bool) -> Self {
+        Self {
+            group: group.0,
+            interface: interface.0,
+            join,
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! A readiness-based event queue, like epoll, built on file descriptor waitpoints.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use super::{timeout_error, twz_rt_fd_waitpoint, Interest};
+use crate::{
+    error::{ArgumentError, GenericError, TwzError},
+    fd::RawFd,
+    thread::{twz_rt_futex_waitv, FutexWaitWord},
+    Result,
+};
+
+/// A caller-chosen value identifying a registration in an [EventQueue].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Token(pub usize);
+
+/// When an [EventQueue] reports a ready descriptor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Trigger {
+    /// Report the descriptor on every wait for as long as it stays ready.
+    #[default]
+    Level,
+    /// Report the descriptor once each time it becomes ready, or when there is new activity on
+    /// it while it stays ready.
+    Edge,
+}
+
+/// An event delivered by [EventQueue::wait].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Event {
+    /// The token the descriptor was registered with.
+    pub token: Token,
+    /// The descriptor is readable.
+    pub read: bool,
+    /// The descriptor is writable.
+    pub write: bool,
+    /// Checking the descriptor failed, for example because it was closed. The descriptor stays
+    /// registered; with [Trigger::Edge], the error is reported once.
+    pub error: Option<TwzError>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Registration {
+    fd: RawFd,
+    token: Token,
+    interest: Interest,
+    trigger: Trigger,
+    // Waitpoint values at the last edge-triggered report, for WAIT_READ and WAIT_WRITE.
+    reported: [Option<u64>; 2],
+    // Whether an error was reported for an edge-triggered registration.
+    failed: bool,
+}
+
+/// An epoll-like queue of file descriptors, each registered with a [Token], that waits until any
+/// of them is ready.
+///
+/// Waiting checks each descriptor's waitpoint (see [twz_rt_fd_waitpoint]), and if none are
+/// ready, sleeps on all of their waitpoint words at once with [twz_rt_futex_waitv].
+///
+/// The queue needs the `alloc` feature, since it owns its registrations and the words it sleeps
+/// on, both of which grow with the number of registered descriptors.
+#[derive(Debug, Default)]
+pub struct EventQueue {
+    regs: Vec<Registration>,
+    words: Vec<FutexWaitWord<'static>>,
+    next: usize,
+}
+
+const WAIT_KINDS: [crate::bindings::wait_kind; 2] =
+    [crate::bindings::WAIT_READ, crate::bindings::WAIT_WRITE];
+
+impl EventQueue {
+    /// Make a new, empty event queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn find(&self, fd: RawFd) -> Result<usize> {
+        self.regs
+            .iter()
+            .position(|r| r.fd == fd)
+            .ok_or(ArgumentError::BadHandle.into())
+    }
+
+    /// Register fd with the queue. Fails with
+    /// [ArgumentError::InvalidArgument](crate::error::ArgumentError::InvalidArgument) if fd is
+    /// already registered, or if interest includes exceptional conditions, which waitpoints don't
+    /// report.
+    pub fn register(
+        &mut self,
+        fd: RawFd,
+        token: Token,
+        interest: Interest,
+        trigger: Trigger,
+    ) -> Result<()> {
+        if interest.except || self.find(fd).is_ok() {
+            return Err(ArgumentError::InvalidArgument.into());
+        }
+        self.regs.push(Registration {
+            fd,
+            token,
+            interest,
+            trigger,
+            reported: [None; 2],
+            failed: false,
+        });
+        Ok(())
+    }
+
+    /// Change the token, interest, and trigger of a registered descriptor. Fails with
+    /// [ArgumentError::BadHandle](crate::error::ArgumentError::BadHandle) if fd is not
+    /// registered, and with
+    /// [ArgumentError::InvalidArgument](crate::error::ArgumentError::InvalidArgument) if interest
+    /// includes exceptional conditions.
+    pub fn modify(
+        &mut self,
+        fd: RawFd,
+        token: Token,
+        interest: Interest,
+        trigger: Trigger,
+    ) -> Result<()> {
+        if interest.except {
+            return Err(ArgumentError::InvalidArgument.into());
+        }
+        let idx = self.find(fd)?;
+        self.regs[idx] = Registration {
+            fd,
+            token,
+            interest,
+            trigger,
+            reported: [None; 2],
+            failed: false,
+        };
+        Ok(())
+    }
+
+    /// Remove fd from the queue. Fails with
+    /// [ArgumentError::BadHandle](crate::error::ArgumentError::BadHandle) if fd is not
+    /// registered.
+    pub fn deregister(&mut self, fd: RawFd) -> Result<()> {
+        let idx = self.find(fd)?;
+        self.regs.swap_remove(idx);
+        Ok(())
+    }
+
+    /// The number of registered descriptors.
+    pub fn len(&self) -> usize {
+        self.regs.len()
+    }
+
+    /// Returns true if no descriptors are registered.
+    pub fn is_empty(&self) -> bool {
+        self.regs.is_empty()
+    }
+
+    /// Wait until at least one registered descriptor is ready, filling events with up to
+    /// events.len() events, and returning the number filled. Ready descriptors that don't fit are
+    /// reported by later calls. A descriptor that can't be checked is reported with
+    /// [Event::error] set. A timeout of None waits indefinitely. If the timeout expires with
+    /// nothing ready, returns [GenericError::WouldBlock] for a zero timeout, and
+    /// [GenericError::TimedOut] otherwise. If there is nothing to wait for, because no registered
+    /// descriptor has read or write interest or every one has failed, returns 0 immediately
+    /// instead of waiting.
+    pub fn wait(&mut self, events: &mut [Event], timeout: Option<Duration>) -> Result<usize> {
+        if events.is_empty() {
+            return Err(ArgumentError::InvalidArgument.into());
+        }
+        let deadline = timeout.map(|t| crate::time::twz_rt_get_monotonic_time() + t);
+        loop {
+            let count = self.collect(events)?;
+            if count > 0 {
+                return Ok(count);
+            }
+            if self.words.is_empty() {
+                return Ok(0);
+            }
+            let remaining =
+                deadline.map(|d| d.saturating_sub(crate::time::twz_rt_get_monotonic_time()));
+            if remaining == Some(Duration::ZERO) {
+                return Err(timeout_error(timeout));
+            }
+            match twz_rt_futex_waitv(&self.words, remaining) {
+                Ok(_) | Err(TwzError::Generic(GenericError::TimedOut)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Check every registration's waitpoints, filling events with ready descriptors, and
+    /// collecting the words to sleep on if none are ready.
+    fn collect(&mut self, events: &mut [Event]) -> Result<usize> {
+        self.words.clear();
+        let mut count = 0;
+        let nr_regs = self.regs.len();
+        for i in 0..nr_regs {
+            let idx = (self.next + i) % nr_regs;
+            let reg = &mut self.regs[idx];
+            let mut ready = [false; 2];
+            let mut vals = [None; 2];
+            let mut error = None;
+            let nr_words = self.words.len();
+            for (k, kind) in WAIT_KINDS.iter().enumerate() {
+                let wanted = if k == 0 {
+                    reg.interest.read
+                } else {
+                    reg.interest.write
+                };
+                if !wanted {
+                    continue;
+                }
+                let (point, val, is_ready) = match twz_rt_fd_waitpoint(reg.fd, *kind) {
+                    Ok(wp) => wp,
+                    Err(e) => {
+                        error = Some(e);
+                        break;
+                    }
+                };
+                let fresh = reg.trigger == Trigger::Level || reg.reported[k] != Some(val);
+                if is_ready && fresh {
+                    ready[k] = true;
+                    vals[k] = Some(val);
+                } else {
+                    if !is_ready {
+                        reg.reported[k] = None;
+                    }
+                    // Safety: the word is valid while the descriptor is open, and words is
+                    // cleared before each use.
+                    self.words
+                        .push(unsafe { FutexWaitWord::from_ptr64(point, val) });
+                }
+            }
+            if error.is_some() {
+                // Don't sleep on the descriptor's other waitpoint.
+                self.words.truncate(nr_words);
+                if reg.trigger == Trigger::Edge && reg.failed {
+                    continue;
+                }
+                ready = [false; 2];
+                vals = [None; 2];
+            } else {
+                reg.failed = false;
+                if !ready[0] && !ready[1] {
+                    continue;
+                }
+            }
+            if count == events.len() {
+                // Out of room; start here next time so this descriptor isn't starved.
+                self.next = idx;
+                return Ok(count);
+            }
+            for (reported, val) in reg.reported.iter_mut().zip(vals) {
+                if val.is_some() {
+                    *reported = val;
+                }
+            }
+            reg.failed = error.is_some();
+            events[count] = Event {
+                token: reg.token,
+                read: ready[0],
+                write: ready[1],
+                error,
+            };
+            count += 1;
+        }
+        if nr_regs > 0 {
+            self.next = (self.next + 1) % nr_regs;
+        }
+        Ok(count)
+    }
+}
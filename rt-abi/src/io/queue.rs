@@ -0,0 +1,313 @@
+//! Batched I/O through shared submission and completion rings, like io_uring.
+
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use super::{IoCtx, IoSlice, SeekFrom};
+use crate::{
+    bindings::{io_cqe, io_queue_header, io_sqe},
+    error::{RawTwzError, ResourceError},
+    fd::{ProtKind, RawFd, SocketAddress},
+    nk,
+    object::{MapFlags, ObjID, ObjectHandle},
+    Result,
+};
+
+/// An operation to submit to an [IoQueue].
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct Submission(io_sqe);
+
+impl Submission {
+    fn new(op: crate::bindings::io_op, fd: RawFd) -> Self {
+        // Safety: io_sqe is plain data, for which all zeros is valid.
+        let mut sqe: io_sqe = unsafe { core::mem::zeroed() };
+        sqe.op = op;
+        sqe.fd = fd;
+        Self(sqe)
+    }
+
+    /// An operation that does nothing.
+    pub fn nop() -> Self {
+        Self::new(crate::bindings::IO_OP_NOP, 0)
+    }
+
+    /// Read into buf, as [twz_rt_fd_pread](super::twz_rt_fd_pread).
+    ///
+    /// # Safety
+    /// buf must remain valid, and not otherwise accessed, until the operation completes or the
+    /// queue is dropped.
+    pub unsafe fn read(fd: RawFd, buf: &mut [u8], ctx: IoCtx) -> Self {
+        let mut this = Self::new(crate::bindings::IO_OP_READ, fd);
+        this.0.ctx = ctx.0;
+        this.0.buf = buf.as_mut_ptr().cast();
+        this.0.len = buf.len();
+        this
+    }
+
+    /// Write buf, as [twz_rt_fd_pwrite](super::twz_rt_fd_pwrite).
+    ///
+    /// # Safety
+    /// buf must remain valid, and not be modified, until the operation completes or the queue is
+    /// dropped.
+    pub unsafe fn write(fd: RawFd, buf: &[u8], ctx: IoCtx) -> Self {
+        let mut this = Self::new(crate::bindings::IO_OP_WRITE, fd);
+        this.0.ctx = ctx.0;
+        this.0.buf = buf.as_ptr() as *mut _;
+        this.0.len = buf.len();
+        this
+    }
+
+    /// Read into multiple buffers, as [twz_rt_fd_preadv](super::twz_rt_fd_preadv).
+    ///
+    /// # Safety
+    /// ios, and the buffers it refers to, must remain valid, and not otherwise be accessed,
+    /// until the operation completes or the queue is dropped.
+    pub unsafe fn readv(fd: RawFd, ios: &[IoSlice], ctx: IoCtx) -> Self {
+        let mut this = Self::new(crate::bindings::IO_OP_READV, fd);
+        this.0.ctx = ctx.0;
+        this.0.buf = ios.as_ptr() as *mut _;
+        this.0.len = ios.len();
+        this
+    }
+
+    /// Write multiple buffers, as [twz_rt_fd_pwritev](super::twz_rt_fd_pwritev).
+    ///
+    /// # Safety
+    /// ios, and the buffers it refers to, must remain valid, and not be modified, until the
+    /// operation completes or the queue is dropped.
+    pub unsafe fn writev(fd: RawFd, ios: &[IoSlice], ctx: IoCtx) -> Self {
+        let mut this = Self::new(crate::bindings::IO_OP_WRITEV, fd);
+        this.0.ctx = ctx.0;
+        this.0.buf = ios.as_ptr() as *mut _;
+        this.0.len = ios.len();
+        this
+    }
+
+    /// Seek, as [twz_rt_fd_seek](super::twz_rt_fd_seek).
+    pub fn seek(fd: RawFd, seek: SeekFrom) -> Self {
+        let mut this = Self::new(crate::bindings::IO_OP_SEEK, fd);
+        (this.0.whence, this.0.arg) = seek.whence_offset();
+        this
+    }
+
+    /// Sync, as [twz_rt_fd_sync](crate::fd::twz_rt_fd_sync).
+    pub fn sync(fd: RawFd) -> Self {
+        Self::new(crate::bindings::IO_OP_SYNC, fd)
+    }
+
+    /// Accept a connection on a listening socket. The completion's value is the new descriptor.
+    pub fn accept(fd: RawFd, flags: u32) -> Self {
+        let mut this = Self::new(crate::bindings::IO_OP_ACCEPT, fd);
+        this.0.arg = flags as i64;
+        this
+    }
+
+    /// Connect a new socket to addr. The completion's value is the new descriptor.
+    pub fn connect(addr: SocketAddress, flags: u32, prot: ProtKind) -> Self {
+        let mut this = Self::new(crate::bindings::IO_OP_CONNECT, 0);
+        this.0.arg = flags as i64;
+        this.0.sock = crate::bindings::socket_bind_info {
+            addr: addr.0,
+            prot: prot as u32,
+        };
+        this
+    }
+
+    /// Set a value to identify this operation's [Completion].
+    pub fn user_data(mut self, user_data: u64) -> Self {
+        self.0.user_data = user_data;
+        self
+    }
+}
+
+/// The result of an operation submitted to an [IoQueue].
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct Completion(io_cqe);
+
+impl Completion {
+    /// The user data of the submission.
+    pub fn user_data(&self) -> u64 {
+        self.0.user_data
+    }
+
+    /// The result of the operation: bytes transferred for reads and writes, the new position
+    /// for seeks, and the new descriptor for accept and connect.
+    pub fn result(&self) -> Result<usize> {
+        self.0.result.into()
+    }
+}
+
+impl core::fmt::Debug for Completion {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Completion")
+            .field("user_data", &self.user_data())
+            .field("result", &self.result())
+            .finish()
+    }
+}
+
+/// A pair of submission and completion rings shared with the runtime. Operations are queued
+/// with [IoQueue::push], started in a batch with [IoQueue::submit], and their results collected
+/// with [IoQueue::pop].
+///
+/// Dropping the queue destroys it, cancelling operations that haven't completed, and waits for
+/// operations in progress to finish or be cancelled. Once the drop returns, the runtime no longer
+/// accesses any submitted buffer.
+pub struct IoQueue {
+    handle: ObjectHandle,
+    sq_pending: u32,
+}
+
+impl IoQueue {
+    /// Create a new queue with room for at least sq_entries submissions and cq_entries
+    /// completions.
+    pub fn new(sq_entries: u32, cq_entries: u32) -> Result<Self> {
+        let res = unsafe {
+            nk!(crate::bindings::twz_rt_io_queue_create(
+                sq_entries, cq_entries
+            ))
+        };
+        let r = RawTwzError::new(res.err);
+        if !r.is_success() {
+            return Err(r.error());
+        }
+        match crate::object::twz_rt_map_object(ObjID::new(res.val), MapFlags::rw_volatile()) {
+            Ok(handle) => Ok(Self {
+                handle,
+                sq_pending: 0,
+            }),
+            Err(e) => {
+                unsafe { nk!(crate::bindings::twz_rt_io_queue_destroy(res.val)) };
+                Err(e)
+            }
+        }
+    }
+
+    /// The ID of the queue object.
+    pub fn id(&self) -> ObjID {
+        self.handle.id()
+    }
+
+    fn header(&self) -> *mut io_queue_header {
+        // The runtime places the header at the start of the object's data.
+        self.handle.start().cast()
+    }
+
+    /// Get the ring geometry, which is fixed when the queue is created: entry count and byte
+    /// offset of the ring.
+    fn ring(&self, sq: bool) -> (u64, usize) {
+        let hdr = self.header();
+        // Safety: these fields are written only by the runtime at creation.
+        unsafe {
+            if sq {
+                ((*hdr).sq_entries as u64, (*hdr).sq_offset as usize)
+            } else {
+                ((*hdr).cq_entries as u64, (*hdr).cq_offset as usize)
+            }
+        }
+    }
+
+    fn index(&self, field: *mut u64) -> &AtomicU64 {
+        // Safety: the header's indices are atomics shared with the runtime, and live as long as
+        // the handle.
+        unsafe { AtomicU64::from_ptr(field) }
+    }
+
+    /// Queue an operation, to be started by the next call to [IoQueue::submit]. Fails with
+    /// [ResourceError::OutOfResources] if the submission ring is full.
+    pub fn push(&mut self, sub: Submission) -> Result<()> {
+        let hdr = self.header();
+        let (entries, offset) = self.ring(true);
+        let head = self
+            .index(unsafe { &raw mut (*hdr).sq_head })
+            .load(Ordering::Acquire);
+        let tail = self
+            .index(unsafe { &raw mut (*hdr).sq_tail })
+            .load(Ordering::Relaxed);
+        if tail - head >= entries {
+            return Err(ResourceError::OutOfResources.into());
+        }
+        let slot = (tail % entries) as usize;
+        // Safety: the slot is in the submission ring, and isn't visible to the runtime until
+        // the tail is advanced.
+        unsafe {
+            let sq = self.handle.start().add(offset).cast::<io_sqe>();
+            sq.add(slot).write(sub.0);
+        }
+        self.index(unsafe { &raw mut (*hdr).sq_tail })
+            .store(tail + 1, Ordering::Release);
+        self.sq_pending += 1;
+        Ok(())
+    }
+
+    /// Start the queued operations, without waiting for any to complete. Returns the number of
+    /// operations started.
+    pub fn submit(&mut self) -> Result<usize> {
+        self.submit_and_wait(0, Some(Duration::ZERO))
+    }
+
+    /// Start the queued operations, and wait until at least min_complete completions are
+    /// available or the timeout expires. Returns the number of operations started.
+    pub fn submit_and_wait(
+        &mut self,
+        min_complete: u32,
+        timeout: Option<Duration>,
+    ) -> Result<usize> {
+        let res: Result<usize> = unsafe {
+            nk!(crate::bindings::twz_rt_io_queue_enter(
+                self.handle.id().raw(),
+                self.sq_pending,
+                min_complete,
+                timeout.into()
+            )
+            .into())
+        };
+        let submitted = res?;
+        self.sq_pending -= (submitted as u32).min(self.sq_pending);
+        Ok(submitted)
+    }
+
+    /// Take the next available completion, if any.
+    pub fn pop(&mut self) -> Option<Completion> {
+        let hdr = self.header();
+        let (entries, offset) = self.ring(false);
+        let head = self
+            .index(unsafe { &raw mut (*hdr).cq_head })
+            .load(Ordering::Relaxed);
+        let tail = self
+            .index(unsafe { &raw mut (*hdr).cq_tail })
+            .load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+        let slot = (head % entries) as usize;
+        // Safety: the slot is in the completion ring, and was filled in before the runtime
+        // advanced the tail past it.
+        let cqe = unsafe {
+            let cq = self.handle.start().add(offset).cast::<io_cqe>();
+            cq.add(slot).read()
+        };
+        self.index(unsafe { &raw mut (*hdr).cq_head })
+            .store(head + 1, Ordering::Release);
+        Some(Completion(cqe))
+    }
+
+    /// Iterate over the available completions, taking each one.
+    pub fn completions(&mut self) -> impl Iterator<Item = Completion> + '_ {
+        core::iter::from_fn(|| self.pop())
+    }
+}
+
+impl Drop for IoQueue {
+    fn drop(&mut self) {
+        unsafe {
+            nk!(crate::bindings::twz_rt_io_queue_destroy(
+                self.handle.id().raw()
+            ))
+        };
+    }
+}
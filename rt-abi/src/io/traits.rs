@@ -0,0 +1,416 @@
+//! Read, Write, Seek, and BufRead traits, mirroring those in std::io, for use without std.
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+use super::{
+    twz_rt_fd_pread, twz_rt_fd_preadv, twz_rt_fd_pwrite, twz_rt_fd_pwritev, twz_rt_fd_seek, IoCtx,
+    IoSlice, SeekFrom,
+};
+#[cfg(feature = "alloc")]
+use crate::error::ArgumentError;
+use crate::{
+    error::{GenericError, IoError, TwzError},
+    fd::OwnedFd,
+    Result,
+};
+
+/// The most buffers passed to the runtime in one vectored operation. Any more are left for the
+/// next call, as a short read or write.
+const MAX_IO_SLICES: usize = 16;
+
+#[cfg(feature = "alloc")]
+const READ_CHUNK_LEN: usize = 4096;
+
+pub(crate) fn is_interrupted(e: &TwzError) -> bool {
+    *e == TwzError::Generic(GenericError::Interrupted)
+}
+
+/// A source of bytes, like std::io::Read.
+pub trait Read {
+    /// Read into buf, returning the number of bytes read, which may be fewer than buf.len(). A
+    /// return of 0 for a non-empty buf indicates the end of the data.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Read into each of bufs in turn, returning the total number of bytes read. The default
+    /// implementation reads into the first non-empty buffer.
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        match bufs.iter_mut().find(|b| !b.is_empty()) {
+            Some(buf) => self.read(buf),
+            None => Ok(0),
+        }
+    }
+
+    /// Fill buf completely, retrying short and interrupted reads. Fails with
+    /// [IoError::UnexpectedEof] if the data ends first, in which case the contents of buf are
+    /// unspecified.
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf) {
+                Ok(0) => return Err(IoError::UnexpectedEof.into()),
+                Ok(n) => buf = &mut buf[n..],
+                Err(e) if is_interrupted(&e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Read until the end of the data, appending to buf and returning the number of bytes read.
+    /// Interrupted reads are retried. On error, the bytes read so far remain in buf.
+    #[cfg(feature = "alloc")]
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        let start = buf.len();
+        loop {
+            let len = buf.len();
+            buf.resize(len + READ_CHUNK_LEN, 0);
+            let res = self.read(&mut buf[len..]);
+            buf.truncate(len + *res.as_ref().unwrap_or(&0));
+            match res {
+                Ok(0) => return Ok(buf.len() - start),
+                Ok(_) => {}
+                Err(e) if is_interrupted(&e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Read until the end of the data, appending to buf, which is left unchanged if the data is
+    /// not valid UTF-8 (failing with
+    /// [ArgumentError::InvalidArgument](crate::error::ArgumentError::InvalidArgument)).
+    #[cfg(feature = "alloc")]
+    fn read_to_string(&mut self, buf: &mut String) -> Result<usize> {
+        let mut bytes = Vec::new();
+        let n = self.read_to_end(&mut bytes)?;
+        let s = core::str::from_utf8(&bytes).map_err(|_| ArgumentError::InvalidArgument)?;
+        buf.push_str(s);
+        Ok(n)
+    }
+
+    /// Borrow this reader, for passing to functions that take a reader by value.
+    fn by_ref(&mut self) -> &mut Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+}
+
+/// A sink for bytes, like std::io::Write.
+pub trait Write {
+    /// Write from buf, returning the number of bytes written, which may be fewer than buf.len().
+    fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+    /// Write each of bufs in turn, returning the total number of bytes written. The default
+    /// implementation writes the first non-empty buffer.
+    fn write_vectored(&mut self, bufs: &[&[u8]]) -> Result<usize> {
+        match bufs.iter().find(|b| !b.is_empty()) {
+            Some(buf) => self.write(buf),
+            None => Ok(0),
+        }
+    }
+
+    /// Push out any data buffered by this writer.
+    fn flush(&mut self) -> Result<()>;
+
+    /// Write all of buf, retrying short and interrupted writes. Fails with [IoError::DataLoss] if
+    /// a write makes no progress.
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf) {
+                Ok(0) => return Err(IoError::DataLoss.into()),
+                Ok(n) => buf = &buf[n..],
+                Err(e) if is_interrupted(&e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Write formatted output, as with the write! macro.
+    fn write_fmt(&mut self, args: core::fmt::Arguments<'_>) -> Result<()> {
+        struct Adapter<'a, W: ?Sized> {
+            inner: &'a mut W,
+            error: Result<()>,
+        }
+
+        impl<W: Write + ?Sized> core::fmt::Write for Adapter<'_, W> {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                self.inner.write_all(s.as_bytes()).map_err(|e| {
+                    self.error = Err(e);
+                    core::fmt::Error
+                })
+            }
+        }
+
+        let mut adapter = Adapter {
+            inner: self,
+            error: Ok(()),
+        };
+        match core::fmt::write(&mut adapter, args) {
+            Ok(()) => Ok(()),
+            Err(_) => adapter.error.and(Err(GenericError::Other.into())),
+        }
+    }
+
+    /// Borrow this writer, for passing to functions that take a writer by value.
+    fn by_ref(&mut self) -> &mut Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+}
+
+/// A cursor that can be moved within a stream of bytes, like std::io::Seek.
+pub trait Seek {
+    /// Move the cursor, returning the new position from the start.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64>;
+
+    /// Move the cursor to the start.
+    fn rewind(&mut self) -> Result<()> {
+        self.seek(SeekFrom::Start(0)).map(|_| ())
+    }
+
+    /// Get the current position from the start.
+    fn stream_position(&mut self) -> Result<u64> {
+        self.seek(SeekFrom::Current(0))
+    }
+}
+
+/// A [Read] with an internal buffer, like std::io::BufRead.
+pub trait BufRead: Read {
+    /// Get the buffered data, filling the buffer from the underlying source if it is empty. An
+    /// empty return indicates the end of the data.
+    fn fill_buf(&mut self) -> Result<&[u8]>;
+
+    /// Mark amt bytes of the buffered data as read, so they are not returned again.
+    fn consume(&mut self, amt: usize);
+
+    /// Returns true if there is more data to read.
+    fn has_data_left(&mut self) -> Result<bool> {
+        self.fill_buf().map(|b| !b.is_empty())
+    }
+
+    /// Read up to and including delim, or the end of the data, appending to buf and returning the
+    /// number of bytes read. Interrupted reads are retried.
+    #[cfg(feature = "alloc")]
+    fn read_until(&mut self, delim: u8, buf: &mut Vec<u8>) -> Result<usize> {
+        let mut total = 0;
+        loop {
+            let (done, used) = {
+                let avail = match self.fill_buf() {
+                    Ok(avail) => avail,
+                    Err(e) if is_interrupted(&e) => continue,
+                    Err(e) => return Err(e),
+                };
+                match avail.iter().position(|&b| b == delim) {
+                    Some(idx) => {
+                        buf.extend_from_slice(&avail[..=idx]);
+                        (true, idx + 1)
+                    }
+                    None => {
+                        buf.extend_from_slice(avail);
+                        (avail.is_empty(), avail.len())
+                    }
+                }
+            };
+            self.consume(used);
+            total += used;
+            if done {
+                return Ok(total);
+            }
+        }
+    }
+
+    /// Read up to and including the next newline, or the end of the data, appending to buf.
+    /// Fails with [ArgumentError::InvalidArgument](crate::error::ArgumentError::InvalidArgument),
+    /// leaving buf unchanged, if the line is not valid UTF-8.
+    #[cfg(feature = "alloc")]
+    fn read_line(&mut self, buf: &mut String) -> Result<usize> {
+        let mut bytes = Vec::new();
+        let n = self.read_until(b'\n', &mut bytes)?;
+        let s = core::str::from_utf8(&bytes).map_err(|_| ArgumentError::InvalidArgument)?;
+        buf.push_str(s);
+        Ok(n)
+    }
+}
+
+/// Collect up to [MAX_IO_SLICES] non-empty buffers for a vectored operation.
+fn io_slices(
+    bufs: impl Iterator<Item = (*mut u8, usize)>,
+    out: &mut [IoSlice; MAX_IO_SLICES],
+) -> &[IoSlice] {
+    let mut nr = 0;
+    for (base, len) in bufs.filter(|(_, len)| *len > 0).take(MAX_IO_SLICES) {
+        out[nr] = IoSlice {
+            iov_base: base.cast(),
+            iov_len: len,
+        };
+        nr += 1;
+    }
+    &out[..nr]
+}
+
+fn empty_io_slices() -> [IoSlice; MAX_IO_SLICES] {
+    [IoSlice {
+        iov_base: core::ptr::null_mut(),
+        iov_len: 0,
+    }; MAX_IO_SLICES]
+}
+
+// Descriptors read, write, and seek at their internal position, so a shared reference is enough,
+// as with std's &File.
+impl Read for &OwnedFd {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        twz_rt_fd_pread(self.as_raw(), buf, &mut IoCtx::default())
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        let mut ios = empty_io_slices();
+        let ios = io_slices(bufs.iter_mut().map(|b| (b.as_mut_ptr(), b.len())), &mut ios);
+        if ios.is_empty() {
+            return Ok(0);
+        }
+        twz_rt_fd_preadv(self.as_raw(), ios, &mut IoCtx::default())
+    }
+}
+
+impl Write for &OwnedFd {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        twz_rt_fd_pwrite(self.as_raw(), buf, &mut IoCtx::default())
+    }
+
+    fn write_vectored(&mut self, bufs: &[&[u8]]) -> Result<usize> {
+        let mut ios = empty_io_slices();
+        let ios = io_slices(
+            bufs.iter().map(|b| (b.as_ptr() as *mut u8, b.len())),
+            &mut ios,
+        );
+        if ios.is_empty() {
+            return Ok(0);
+        }
+        twz_rt_fd_pwritev(self.as_raw(), ios, &mut IoCtx::default())
+    }
+
+    /// Descriptors are unbuffered, so this does nothing. Use
+    /// [twz_rt_fd_sync](crate::fd::twz_rt_fd_sync) to make written data durable.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for &OwnedFd {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        twz_rt_fd_seek(self.as_raw(), pos).map(|p| p as u64)
+    }
+}
+
+impl Read for OwnedFd {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (&*self).read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        (&*self).read_vectored(bufs)
+    }
+}
+
+impl Write for OwnedFd {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (&*self).write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[&[u8]]) -> Result<usize> {
+        (&*self).write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (&*self).flush()
+    }
+}
+
+impl Seek for OwnedFd {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        (&*self).seek(pos)
+    }
+}
+
+impl<R: Read + ?Sized> Read for &mut R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        (**self).read_vectored(bufs)
+    }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[&[u8]]) -> Result<usize> {
+        (**self).write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+}
+
+impl<S: Seek + ?Sized> Seek for &mut S {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        (**self).seek(pos)
+    }
+}
+
+impl<B: BufRead + ?Sized> BufRead for &mut B {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        (**self).fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        (**self).consume(amt)
+    }
+}
+
+impl Read for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = buf.len().min(self.len());
+        let (head, tail) = self.split_at(n);
+        buf[..n].copy_from_slice(head);
+        *self = tail;
+        Ok(n)
+    }
+}
+
+impl BufRead for &[u8] {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        Ok(self)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        *self = &self[amt.min(self.len())..];
+    }
+}
+
+/// Writes fill the slice from the front, advancing past the written bytes. Writing to a full
+/// slice returns 0.
+impl Write for &mut [u8] {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = buf.len().min(self.len());
+        let (head, tail) = core::mem::take(self).split_at_mut(n);
+        head.copy_from_slice(&buf[..n]);
+        *self = tail;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
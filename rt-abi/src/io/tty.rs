@@ -0,0 +1,367 @@
+//! Ptys: creating them, their terminal settings and window size, and job control.
+
+use core::time::Duration;
+
+use super::{
+    config::{
+        ConfigRegister, Foreground, Signal, SignalFlags, Status, StatusFlags, TerminalSettings,
+        WindowSize,
+    },
+    timeout_error, twz_rt_fd_waitpoint,
+};
+use crate::{
+    error::{GenericError, TwzError},
+    fd::{OwnedFd, RawFd},
+    object::{twz_rt_create_object, twz_rt_delete_object, ObjID, ObjectCreate},
+    thread::{twz_rt_futex_waitv, FutexWaitWord},
+    Result,
+};
+
+/// Terminal settings, the value of [TerminalSettings].
+pub use crate::bindings::tty_termios as Termios;
+/// Terminal window size, the value of [WindowSize].
+pub use crate::bindings::tty_winsize as Winsize;
+
+bitflags::bitflags! {
+    /// Input flags of [Termios].
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+    pub struct InputFlags : crate::bindings::tty_flags {
+        /// Translate carriage return to newline on input.
+        const ICRNL = crate::bindings::TTY_IFLAG_ICRNL;
+        /// Translate newline to carriage return on input.
+        const INLCR = crate::bindings::TTY_IFLAG_INLCR;
+        /// Ignore carriage return on input.
+        const IGNCR = crate::bindings::TTY_IFLAG_IGNCR;
+        /// Enable XON/XOFF flow control on output.
+        const IXON = crate::bindings::TTY_IFLAG_IXON;
+        /// Strip the eighth bit of input characters.
+        const ISTRIP = crate::bindings::TTY_IFLAG_ISTRIP;
+        /// Signal an interrupt on break.
+        const BRKINT = crate::bindings::TTY_IFLAG_BRKINT;
+        /// Enable input parity checking.
+        const INPCK = crate::bindings::TTY_IFLAG_INPCK;
+    }
+}
+
+bitflags::bitflags! {
+    /// Output flags of [Termios].
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+    pub struct OutputFlags : crate::bindings::tty_flags {
+        /// Enable output processing.
+        const OPOST = crate::bindings::TTY_OFLAG_OPOST;
+        /// Translate newline to carriage return and newline on output.
+        const ONLCR = crate::bindings::TTY_OFLAG_ONLCR;
+    }
+}
+
+bitflags::bitflags! {
+    /// Local flags of [Termios].
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+    pub struct LocalFlags : crate::bindings::tty_flags {
+        /// Echo input characters.
+        const ECHO = crate::bindings::TTY_LFLAG_ECHO;
+        /// Echo newline, even if ECHO is not set.
+        const ECHONL = crate::bindings::TTY_LFLAG_ECHONL;
+        /// Canonical mode: input is made available a line at a time, with line editing.
+        const ICANON = crate::bindings::TTY_LFLAG_ICANON;
+        /// Generate signals for the INTR, QUIT, and SUSP characters.
+        const ISIG = crate::bindings::TTY_LFLAG_ISIG;
+        /// Enable extended input processing, such as the LNEXT character.
+        const IEXTEN = crate::bindings::TTY_LFLAG_IEXTEN;
+        /// In canonical mode, the ERASE character erases the preceding character.
+        const ECHOE = crate::bindings::TTY_LFLAG_ECHOE;
+        /// In canonical mode, the KILL character erases the current line.
+        const ECHOK = crate::bindings::TTY_LFLAG_ECHOK;
+    }
+}
+
+/// The control characters of [Termios], as indices into its cc array.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u32)]
+pub enum ControlChar {
+    /// Interrupt character. With [LocalFlags::ISIG], input of it raises
+    /// [SignalFlags::INTERRUPT].
+    Intr = crate::bindings::TTY_CC_VINTR,
+    /// Quit character. With [LocalFlags::ISIG], input of it raises [SignalFlags::QUIT].
+    Quit = crate::bindings::TTY_CC_VQUIT,
+    /// In canonical mode, the character that erases the preceding character.
+    Erase = crate::bindings::TTY_CC_VERASE,
+    /// In canonical mode, the character that erases the current line.
+    Kill = crate::bindings::TTY_CC_VKILL,
+    /// In canonical mode, the end-of-file character. It makes the current line available
+    /// without a newline, so on an empty line, a read returns 0.
+    Eof = crate::bindings::TTY_CC_VEOF,
+    /// In non-canonical mode, the read timeout in tenths of a second.
+    Time = crate::bindings::TTY_CC_VTIME,
+    /// In non-canonical mode, the minimum number of bytes for a read to return.
+    Min = crate::bindings::TTY_CC_VMIN,
+    /// Suspend character. With [LocalFlags::ISIG], input of it raises [SignalFlags::SUSPEND].
+    Susp = crate::bindings::TTY_CC_VSUSP,
+    /// In canonical mode with [LocalFlags::IEXTEN], the character that erases the preceding
+    /// word.
+    Werase = crate::bindings::TTY_CC_VWERASE,
+    /// With [LocalFlags::IEXTEN], the character that makes the next input character lose any
+    /// special meaning.
+    Lnext = crate::bindings::TTY_CC_VLNEXT,
+}
+
+impl Termios {
+    /// Typical settings for an interactive terminal: canonical mode with echo, signals from
+    /// input, and newline translation.
+    pub fn cooked() -> Self {
+        let mut termios = Self {
+            iflag: (InputFlags::ICRNL | InputFlags::IXON | InputFlags::BRKINT).bits(),
+            oflag: (OutputFlags::OPOST | OutputFlags::ONLCR).bits(),
+            lflag: (LocalFlags::ECHO
+                | LocalFlags::ECHOE
+                | LocalFlags::ECHOK
+                | LocalFlags::ICANON
+                | LocalFlags::ISIG
+                | LocalFlags::IEXTEN)
+                .bits(),
+            cc: [0; crate::bindings::TTY_NCCS as usize],
+            ispeed: DEFAULT_SPEED,
+            ospeed: DEFAULT_SPEED,
+        };
+        for (cc, val) in [
+            (ControlChar::Intr, 0x03),
+            (ControlChar::Quit, 0x1c),
+            (ControlChar::Erase, 0x7f),
+            (ControlChar::Kill, 0x15),
+            (ControlChar::Eof, 0x04),
+            (ControlChar::Time, 0),
+            (ControlChar::Min, 1),
+            (ControlChar::Susp, 0x1a),
+            (ControlChar::Werase, 0x17),
+            (ControlChar::Lnext, 0x16),
+        ] {
+            termios.set_control_char(cc, val);
+        }
+        termios
+    }
+
+    /// Get the input flags.
+    pub fn input_flags(&self) -> InputFlags {
+        InputFlags::from_bits_retain(self.iflag)
+    }
+
+    /// Set the input flags.
+    pub fn set_input_flags(&mut self, flags: InputFlags) {
+        self.iflag = flags.bits();
+    }
+
+    /// Get the output flags.
+    pub fn output_flags(&self) -> OutputFlags {
+        OutputFlags::from_bits_retain(self.oflag)
+    }
+
+    /// Set the output flags.
+    pub fn set_output_flags(&mut self, flags: OutputFlags) {
+        self.oflag = flags.bits();
+    }
+
+    /// Get the local flags.
+    pub fn local_flags(&self) -> LocalFlags {
+        LocalFlags::from_bits_retain(self.lflag)
+    }
+
+    /// Set the local flags.
+    pub fn set_local_flags(&mut self, flags: LocalFlags) {
+        self.lflag = flags.bits();
+    }
+
+    /// Get a control character.
+    pub fn control_char(&self, cc: ControlChar) -> u8 {
+        self.cc[cc as usize]
+    }
+
+    /// Set a control character.
+    pub fn set_control_char(&mut self, cc: ControlChar, val: u8) {
+        self.cc[cc as usize] = val;
+    }
+
+    /// Change to raw mode: input is available byte by byte, unprocessed and not echoed, and
+    /// output is unprocessed. Reads return once a byte is available.
+    pub fn make_raw(&mut self) {
+        self.set_input_flags(
+            self.input_flags()
+                - (InputFlags::ICRNL
+                    | InputFlags::INLCR
+                    | InputFlags::IGNCR
+                    | InputFlags::IXON
+                    | InputFlags::ISTRIP
+                    | InputFlags::BRKINT
+                    | InputFlags::INPCK),
+        );
+        self.set_output_flags(self.output_flags() - OutputFlags::OPOST);
+        self.set_local_flags(
+            self.local_flags()
+                - (LocalFlags::ECHO
+                    | LocalFlags::ECHONL
+                    | LocalFlags::ICANON
+                    | LocalFlags::ISIG
+                    | LocalFlags::IEXTEN),
+        );
+        self.set_control_char(ControlChar::Min, 1);
+        self.set_control_char(ControlChar::Time, 0);
+    }
+}
+
+/// The speed of a pty created by [open_pty], in baud.
+const DEFAULT_SPEED: u32 = 38400;
+
+/// The window size of a pty created by [open_pty].
+pub const DEFAULT_WINSIZE: Winsize = Winsize {
+    rows: 24,
+    cols: 80,
+    xpixel: 0,
+    ypixel: 0,
+};
+
+/// Get the terminal settings of fd.
+pub fn get_termios(fd: RawFd) -> Result<Termios> {
+    TerminalSettings::get(fd)
+}
+
+/// Set the terminal settings of fd.
+pub fn set_termios(fd: RawFd, termios: &Termios) -> Result<()> {
+    TerminalSettings::set(fd, *termios)
+}
+
+/// Restores a terminal's settings when dropped. Returned by [enable_raw_mode].
+#[derive(Debug)]
+pub struct RawModeGuard {
+    fd: RawFd,
+    original: Termios,
+}
+
+impl RawModeGuard {
+    /// The settings from before raw mode was enabled.
+    pub fn original(&self) -> &Termios {
+        &self.original
+    }
+
+    /// Restore the original settings now, returning any error.
+    pub fn restore(self) -> Result<()> {
+        let this = core::mem::ManuallyDrop::new(self);
+        set_termios(this.fd, &this.original)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        let _ = set_termios(self.fd, &self.original);
+    }
+}
+
+/// Put the terminal fd into raw mode (see [Termios::make_raw]) until the returned guard is
+/// dropped.
+pub fn enable_raw_mode(fd: RawFd) -> Result<RawModeGuard> {
+    let original = get_termios(fd)?;
+    let mut raw = original;
+    raw.make_raw();
+    set_termios(fd, &raw)?;
+    Ok(RawModeGuard { fd, original })
+}
+
+/// Get the window size of the terminal fd.
+pub fn get_window_size(fd: RawFd) -> Result<Winsize> {
+    WindowSize::get(fd)
+}
+
+/// Set the window size of the terminal fd, notifying its other descriptors.
+pub fn set_window_size(fd: RawFd, size: Winsize) -> Result<()> {
+    WindowSize::set(fd, size)
+}
+
+/// Wait until the window size of the terminal fd changes, and return the new size. A change
+/// since the last call is returned immediately. A timeout of None waits indefinitely. If the
+/// timeout expires first, returns [GenericError::WouldBlock] for a zero timeout, and
+/// [GenericError::TimedOut] otherwise.
+pub fn wait_window_size_change(fd: RawFd, timeout: Option<Duration>) -> Result<Winsize> {
+    let deadline = timeout.map(|t| crate::time::twz_rt_get_monotonic_time() + t);
+    loop {
+        let (point, val, ready) = twz_rt_fd_waitpoint(fd, crate::bindings::WAIT_SIGNAL)?;
+        // If other signals are pending but not this one, the wait below sleeps until the next
+        // signal is raised.
+        if ready && Signal::get(fd)?.contains(SignalFlags::WINSIZE) {
+            Signal::set(fd, SignalFlags::WINSIZE)?;
+            return get_window_size(fd);
+        }
+        let remaining =
+            deadline.map(|d| d.saturating_sub(crate::time::twz_rt_get_monotonic_time()));
+        if remaining == Some(Duration::ZERO) {
+            return Err(timeout_error(timeout));
+        }
+        // Safety: the word is valid while fd is open, which it is for this call.
+        let word = unsafe { FutexWaitWord::from_ptr64(point, val) };
+        match twz_rt_futex_waitv(&[word], remaining) {
+            Ok(_) | Err(TwzError::Generic(GenericError::TimedOut)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Create a new pty, with [Termios::cooked] settings and a [DEFAULT_WINSIZE] window. Returns the
+/// server and client descriptors, and the ID of the backing object, with which more clients can
+/// be opened with [twz_rt_fd_open_pty_client](crate::fd::twz_rt_fd_open_pty_client).
+///
+/// Closing the descriptors doesn't delete the backing object. Once the server and every client
+/// are closed, delete it with [delete_pty]; otherwise, being volatile, it lasts until the system
+/// restarts.
+pub fn open_pty() -> Result<(OwnedFd, OwnedFd, ObjID)> {
+    open_pty_with(&Termios::cooked(), DEFAULT_WINSIZE)
+}
+
+/// Create a new pty, as [open_pty], with the given settings and window size.
+pub fn open_pty_with(termios: &Termios, winsize: Winsize) -> Result<(OwnedFd, OwnedFd, ObjID)> {
+    let id = twz_rt_create_object(&ObjectCreate::default())?;
+    let open = || {
+        let server = OwnedFd::from_raw(crate::fd::twz_rt_fd_open_pty_server(id.raw(), 0)?);
+        set_termios(server.as_raw(), termios)?;
+        set_window_size(server.as_raw(), winsize)?;
+        let client = OwnedFd::from_raw(crate::fd::twz_rt_fd_open_pty_client(id.raw(), 0)?);
+        Ok((server, client, id))
+    };
+    open().inspect_err(|_| {
+        let _ = twz_rt_delete_object(id);
+    })
+}
+
+/// Delete the backing object of a pty created by [open_pty], given its ID. Call this only once the
+/// server and every client descriptor are closed.
+pub fn delete_pty(id: ObjID) -> Result<()> {
+    twz_rt_delete_object(id)
+}
+
+/// Get the foreground compartment of the pty fd, if any.
+pub fn get_foreground(fd: RawFd) -> Result<Option<ObjID>> {
+    let id = Foreground::get(fd)?;
+    Ok((id.raw() != 0).then_some(id))
+}
+
+/// Set the foreground compartment of the pty fd, which receives the [SignalFlags::INTERRUPT],
+/// [SignalFlags::QUIT], and [SignalFlags::SUSPEND] signals generated by input.
+pub fn set_foreground(fd: RawFd, comp: Option<ObjID>) -> Result<()> {
+    Foreground::set(fd, comp.unwrap_or_default())
+}
+
+/// Get and acknowledge the signals pending on fd.
+pub fn take_signals(fd: RawFd) -> Result<SignalFlags> {
+    let pending = Signal::get(fd)?;
+    if !pending.is_empty() {
+        Signal::set(fd, pending)?;
+    }
+    Ok(pending)
+}
+
+/// Hang up the pty, given its server descriptor. Its clients then read end-of-file, and writes
+/// to them fail with [IoError::Reset](crate::error::IoError::Reset).
+pub fn hangup(server: RawFd) -> Result<()> {
+    Status::set(server, StatusFlags::TERMINATED)
+}
+
+/// Returns true if the pty fd has been hung up, or its server side closed.
+pub fn is_hung_up(fd: RawFd) -> Result<bool> {
+    Ok(Status::get(fd)?.contains(StatusFlags::TERMINATED))
+}
@@ -20,6 +20,8 @@ pub mod object;
 
 pub mod alloc;
 pub mod arch;
+#[cfg(feature = "async")]
+pub mod async_io;
 pub mod debug;
 pub mod exec;
 pub mod fd;
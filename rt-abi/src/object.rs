@@ -377,6 +377,30 @@ impl From<crate::bindings::map_result> for Result<ObjectHandle> {
     }
 }
 
+/// Create a new, empty, unnamed object with the given spec. Returns the ID of the new object.
+pub(crate) fn twz_rt_create_object(spec: &ObjectCreate) -> Result<ObjID> {
+    let spec: object_create = (*spec).into();
+    let res = unsafe {
+        nk!(crate::bindings::twz_rt_create_object(
+            &spec,
+            core::ptr::null(),
+            0,
+            core::ptr::null(),
+            0,
+            core::ptr::null(),
+            0,
+        ))
+    };
+    RawTwzError::new(res.err).result()?;
+    Ok(ObjID::new(res.val))
+}
+
+/// Delete the object given by ID `id`.
+pub(crate) fn twz_rt_delete_object(id: ObjID) -> Result<()> {
+    let handle = twz_rt_map_object(id, MapFlags::READ | MapFlags::WRITE)?;
+    handle.cmd(ObjectCmd::Delete, core::ptr::null_mut::<()>())
+}
+
 /// Map an object given by ID `id` with the given flags.
 pub fn twz_rt_map_object(id: ObjID, flags: MapFlags) -> Result<ObjectHandle> {
     unsafe { nk!(crate::bindings::twz_rt_map_object(id.raw(), flags.bits()).into()) }
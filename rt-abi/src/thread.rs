@@ -83,6 +83,74 @@ pub fn twz_rt_futex_wake(word: &AtomicFutexWord, max: Option<usize>) -> Result<(
     }
 }
 
+/// A word to wait on with [twz_rt_futex_waitv], along with its expected value.
+#[derive(Clone, Copy, Debug)]
+#[repr(transparent)]
+pub struct FutexWaitWord<'a>(
+    crate::bindings::futex_waitv_word,
+    core::marker::PhantomData<&'a ()>,
+);
+
+impl<'a> FutexWaitWord<'a> {
+    /// Wait on a 32-bit word.
+    pub fn new32(word: &'a core::sync::atomic::AtomicU32, expected: u32) -> Self {
+        Self(
+            crate::bindings::futex_waitv_word {
+                ptr: word.as_ptr().cast(),
+                expected: expected as u64,
+                width: crate::bindings::FUTEX_WIDTH_32,
+            },
+            core::marker::PhantomData,
+        )
+    }
+
+    /// Wait on a 64-bit word.
+    pub fn new64(word: &'a core::sync::atomic::AtomicU64, expected: u64) -> Self {
+        Self(
+            crate::bindings::futex_waitv_word {
+                ptr: word.as_ptr().cast(),
+                expected,
+                width: crate::bindings::FUTEX_WIDTH_64,
+            },
+            core::marker::PhantomData,
+        )
+    }
+
+    /// Wait on a 64-bit word given by pointer, such as one returned by
+    /// [twz_rt_fd_waitpoint](crate::io::twz_rt_fd_waitpoint).
+    ///
+    /// # Safety
+    /// The word must remain valid for as long as this object is used.
+    pub unsafe fn from_ptr64(word: *const core::sync::atomic::AtomicU64, expected: u64) -> Self {
+        Self(
+            crate::bindings::futex_waitv_word {
+                ptr: word as *mut _,
+                expected,
+                width: crate::bindings::FUTEX_WIDTH_64,
+            },
+            core::marker::PhantomData,
+        )
+    }
+
+    /// The expected value of the word.
+    pub fn expected(&self) -> u64 {
+        self.0.expected
+    }
+}
+
+/// Sleep until any of the words is woken or does not hold its expected value, and return the
+/// index of that word. As with [twz_rt_futex_wait], the thread is enqueued before the words are
+/// checked, and wakeups may be spurious. Returns
+/// [GenericError::TimedOut](crate::error::GenericError::TimedOut) on timeout.
+pub fn twz_rt_futex_waitv(words: &[FutexWaitWord<'_>], timeout: Option<Duration>) -> Result<usize> {
+    unsafe {
+        nk!(
+            crate::bindings::twz_rt_futex_waitv(words.as_ptr().cast(), words.len(), timeout.into())
+                .into()
+        )
+    }
+}
+
 /// Yield the calling thread.
 pub fn twz_rt_yield() {
     unsafe {
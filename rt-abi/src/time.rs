@@ -70,3 +70,13 @@ impl From<Option<Duration>> for crate::bindings::option_duration {
         }
     }
 }
+
+impl From<crate::bindings::option_duration> for Option<Duration> {
+    fn from(value: crate::bindings::option_duration) -> Self {
+        if value.is_some != 0 {
+            Some(value.dur.into())
+        } else {
+            None
+        }
+    }
+}